@@ -0,0 +1,188 @@
+//! `pandoc-generator CONFIG OUT_DIR`: render the content tree configured
+//! for `pandoc_compile_html!` to a directory, without compiling the crate
+//! using it.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+};
+
+use pandoc_generator_core::{MANIFEST_FILE, export_macro};
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+const MACRO_NAME: &str = "pandoc_compile_html";
+
+const USAGE: &str = "\
+Usage: pandoc-generator [OPTIONS] <CONFIG> <OUT_DIR>
+
+Render the content tree configured in CONFIG to OUT_DIR, each page in the
+directory of its route, with a manifest.json describing the tree.
+
+CONFIG is a Rust file invoking pandoc_compile_html!, or a file holding only
+the macro's arguments.
+
+Options:
+  --mod <NAME>  Render the invocation with `mod_name = NAME` when CONFIG has
+                several
+  --root <DIR>  Resolve relative paths against DIR instead of the crate
+                holding CONFIG
+  -h, --help    Print this help";
+
+struct Args {
+    config: PathBuf,
+    out_dir: PathBuf,
+    mod_name: Option<String>,
+    root: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut positional = Vec::new();
+    let mut mod_name = None;
+    let mut root = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--mod" => mod_name = Some(args.next().ok_or("--mod needs a module name")?),
+            "--root" => root = Some(PathBuf::from(args.next().ok_or("--root needs a directory")?)),
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+
+    let [config, out_dir] = <[PathBuf; 2]>::try_from(positional)
+        .map_err(|_| "expected a configuration file and an output directory".to_string())?;
+    Ok(Some(Args { config, out_dir, mod_name, root }))
+}
+
+/// Arguments of every `pandoc_compile_html!` invocation in `tokens`
+fn invocations(tokens: TokenStream, out: &mut Vec<TokenStream>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if let TokenTree::Group(group) = token {
+            let invoked = match &tokens[..i] {
+                [.., TokenTree::Ident(name), TokenTree::Punct(bang)] => name == MACRO_NAME && bang.as_char() == '!',
+                _ => false,
+            };
+
+            if invoked && group.delimiter() != Delimiter::None {
+                out.push(group.stream());
+            } else {
+                invocations(group.stream(), out);
+            }
+        }
+    }
+}
+
+/// Value of the top-level `mod_name = ...` argument
+fn mod_name(arguments: &TokenStream) -> Option<String> {
+    let tokens: Vec<TokenTree> = arguments.clone().into_iter().collect();
+
+    tokens.windows(3).find_map(|window| match window {
+        [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Ident(value)]
+            if key == "mod_name" && eq.as_char() == '=' =>
+        {
+            Some(value.to_string())
+        }
+        _ => None,
+    })
+}
+
+/// The invocation to render: the only one in `source`, the one named by
+/// `--mod`, or the whole file when it holds no invocation
+fn select(source: TokenStream, wanted: Option<&str>) -> Result<TokenStream, String> {
+    let mut found = Vec::new();
+    invocations(source.clone(), &mut found);
+    if found.is_empty() {
+        return Ok(source);
+    }
+
+    match wanted {
+        Some(wanted) => found
+            .into_iter()
+            .find(|arguments| mod_name(arguments).as_deref() == Some(wanted))
+            .ok_or_else(|| format!("no {}! invocation with `mod_name = {}`", MACRO_NAME, wanted)),
+        None if found.len() == 1 => Ok(found.remove(0)),
+        None => {
+            let names: Vec<String> = found.iter().filter_map(mod_name).collect();
+            Err(format!(
+                "{} {}! invocations, pick one with --mod ({})",
+                found.len(),
+                MACRO_NAME,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// Directory of the crate holding `config`, which the macro would resolve
+/// relative paths against
+fn crate_root(config: &Path) -> PathBuf {
+    let config = fs::canonicalize(config).unwrap_or_else(|_| config.to_path_buf());
+    let dir = config.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    dir.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map_or(dir.clone(), Path::to_path_buf)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let text = fs::read_to_string(&args.config)
+        .map_err(|e| format!("failed to read {}: {}", args.config.display(), e))?;
+    let source = TokenStream::from_str(&text).map_err(|e| format!("{}: {}", args.config.display(), e))?;
+    let arguments = select(source, args.mod_name.as_deref())?;
+
+    let root = args.root.unwrap_or_else(|| crate_root(&args.config));
+    let export = export_macro(arguments, &root).map_err(|err| {
+        let messages: Vec<String> = err
+            .into_iter()
+            .map(|e| match e.span().start() {
+                start if start.line > 0 => {
+                    format!("{}:{}:{}: {}", args.config.display(), start.line, start.column + 1, e)
+                }
+                _ => e.to_string(),
+            })
+            .collect();
+        messages.join("\nerror: ")
+    })?;
+
+    for warning in export.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    export
+        .write_to(&args.out_dir)
+        .map_err(|e| format!("failed to write {}: {}", args.out_dir.display(), e))?;
+
+    println!(
+        "Wrote {} files and {} to {}",
+        export.files().count(),
+        MANIFEST_FILE,
+        args.out_dir.display()
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
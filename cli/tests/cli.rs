@@ -0,0 +1,114 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+fn repo() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+}
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("pandoc-generator-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn pandoc_generator(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_pandoc-generator"))
+        .args(args)
+        .output()
+        .expect("failed to run pandoc-generator")
+}
+
+#[test]
+fn test_renders_a_macro_invocation_by_route() {
+    let out = out_dir("mock");
+    let config = repo().join("tests/integration_mock.rs");
+    let result = pandoc_generator(&[config.to_str().unwrap(), out.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    // The same renderings tests/integration_mock.rs checks in the module
+    assert_eq!(
+        fs::read_to_string(out.join("section/page1/index.html")).unwrap(),
+        "<!-- mock html5 -->\n## Page 1\n\nPage 1 content.\n<!-- /mock -->\n"
+    );
+    assert_eq!(
+        fs::read(out.join("section/page1/index.docx")).unwrap(),
+        b"MOCK docx\n## Page 1\n\nPage 1 content.\n"
+    );
+    assert!(out.join("post/index.html").is_file());
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out.join("manifest.json")).unwrap()).unwrap();
+    let root = &manifest["tree"];
+    assert_eq!(root["name"], "ROOT");
+
+    let site = &root["elements"][0];
+    let names: Vec<&str> = site["elements"].as_array().unwrap().iter().map(|n| n["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["section", "section", "about", "index"]);
+
+    let post = &root["elements"][1];
+    assert_eq!(post["kind"], "html");
+    assert_eq!(post["route"], "/post");
+    assert_eq!(post["meta"]["title"], "Hello World");
+    assert_eq!(post["outputs"]["html5"], "post/index.html");
+    assert_eq!(post["order"], 8);
+
+    let _ = fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_reads_bare_macro_arguments() {
+    let out = out_dir("args");
+    let config = out_dir("args-config").with_extension("txt");
+    fs::write(
+        &config,
+        r#"
+        mod_name = guide,
+        tree_name = GUIDE,
+        content = [ compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide") ],
+        output_format = [Html5, Plain],
+        backend = Mock,
+        cache = false
+        "#,
+    )
+    .unwrap();
+
+    let root = repo();
+    let result = pandoc_generator(&["--root", root.to_str().unwrap(), config.to_str().unwrap(), out.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(fs::read_to_string(out.join("guide/index.html")).unwrap().starts_with("<!-- mock html5 -->"));
+    assert!(fs::read_to_string(out.join("guide/index.txt")).unwrap().starts_with("<!-- mock plain -->"));
+
+    let _ = fs::remove_dir_all(&out);
+    let _ = fs::remove_file(&config);
+}
+
+#[test]
+fn test_errors_point_into_the_configuration() {
+    let out = out_dir("error");
+    let config = out_dir("error-config").with_extension("rs");
+    fs::write(
+        &config,
+        "pandoc_generator::pandoc_compile_html! {\n    mod_name = broken,\n    tree_name = BROKEN,\n    \
+         content = [ compile_from_path(path: \"does/not/exist\", route: \"/\") ],\n    backend = Mock\n}\n",
+    )
+    .unwrap();
+
+    let result = pandoc_generator(&[config.to_str().unwrap(), out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.contains(&format!("{}:4:17: Failed to explore does/not/exist", config.display())),
+        "{}",
+        stderr
+    );
+    assert!(!out.exists());
+
+    let _ = fs::remove_file(&config);
+}
+
+#[test]
+fn test_several_invocations_need_a_module_name() {
+    let config = repo().join("tests/integration_tree.rs");
+    let result = pandoc_generator(&[config.to_str().unwrap(), "unused"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("pick one with --mod"));
+}
@@ -281,6 +281,39 @@ pub fn parse_pandoc_options(tokens: proc_macro2::TokenStream) -> syn::Result<Vec
     Ok(out)
 }
 
+/// Keep only the options that shape the parsed document, so the metadata
+/// pass sees the same AST as the real conversion without writer-only flags.
+pub fn reader_options(options: &[PandocOption]) -> Vec<PandocOption> {
+    options
+        .iter()
+        .filter(|opt| {
+            matches!(
+                opt,
+                PandocOption::DataDir(_)
+                    | PandocOption::Strict
+                    | PandocOption::ParseRaw
+                    | PandocOption::Smart
+                    | PandocOption::OldDashes
+                    | PandocOption::ShiftHeadingLevelBy(_)
+                    | PandocOption::IndentedCodeClasses(_)
+                    | PandocOption::Filter(_)
+                    | PandocOption::LuaFilter(_)
+                    | PandocOption::Normalize
+                    | PandocOption::PreserveTabs
+                    | PandocOption::TabStop(_)
+                    | PandocOption::TrackChanges(_)
+                    | PandocOption::Meta(_, _)
+                    | PandocOption::Citeproc
+                    | PandocOption::Bibliography(_)
+                    | PandocOption::Csl(_)
+                    | PandocOption::CitationAbbreviations(_)
+                    | PandocOption::Sandbox
+            )
+        })
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -340,6 +373,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_reader_options_drop_writer_flags() {
+        let input = quote! { Standalone, TableOfContents, Filter { "f.py" }, Meta { "k", "v" } };
+        let opts = reader_options(&parse_pandoc_options(input).unwrap());
+        assert_eq!(
+            opts,
+            vec![
+                PandocOption::Filter(PathBuf::from("f.py")),
+                PandocOption::Meta("k".to_string(), Some("v".to_string()))
+            ]
+        );
+    }
+
     #[test]
     fn test_unsupported_variant_fails() {
         let input = quote! { SomeUnsupported { "arg" } };
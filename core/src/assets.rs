@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::{Digest, Sha256};
+
+use crate::cache::hex;
+
+/// Route prefix of embedded assets
+const ASSET_ROUTE: &str = "/assets/";
+
+/// Extensions of static files, never converted as sources even when they
+/// are not embedded. Formats pandoc reads, such as html, are left out.
+const STATIC_EXTS: &[&str] = &[
+    "svg", "png", "jpg", "jpeg", "gif", "webp", "avif", "ico", "css", "js", "mjs", "pdf", "woff", "woff2", "ttf",
+    "otf", "mp3", "mp4", "webm",
+];
+
+/// Whether `path` is an image, stylesheet, font or other static file
+pub(crate) fn is_static(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| STATIC_EXTS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// MIME type of a static file, by extension
+pub(crate) fn mime_for(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    match ext.to_lowercase().as_str() {
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Route of a file embedded with `contents`: its name with a content hash,
+/// so the route changes whenever the file does
+pub(crate) fn asset_route(path: &Path, contents: &[u8]) -> String {
+    let hash = hex(&Sha256::digest(contents));
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+
+    match path.extension() {
+        Some(ext) => format!("{}{}.{}.{}", ASSET_ROUTE, stem, &hash[..8], ext.to_string_lossy()),
+        None => format!("{}{}.{}", ASSET_ROUTE, stem, &hash[..8]),
+    }
+}
+
+/// Local file a URL in the page rendered from `src` points at: relative to
+/// the source file, or absolute like the paths pandoc's
+/// rebase_relative_paths writes for the absolute sources
+pub(crate) fn resolve_file(src: &Path, url: &str) -> Option<PathBuf> {
+    let path = match url.starts_with('/') {
+        true => PathBuf::from(url),
+        false => src.parent().unwrap_or(Path::new("")).join(url),
+    };
+
+    Some(path).filter(|path| path.is_file())
+}
+
+/// Standard base64 with padding
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// `data:` URI embedding `contents` of the file at `path`
+pub(crate) fn data_uri(path: &Path, contents: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_for(path), base64(contents))
+}
+
+pub(crate) struct Asset {
+    pub route: String,
+    pub mime: &'static str,
+}
+
+/// Every embedded file, keyed by canonical path
+#[derive(Default)]
+pub(crate) struct Assets {
+    files: BTreeMap<PathBuf, Asset>,
+}
+
+impl Assets {
+    /// Embed `path`, returning its asset
+    pub(crate) fn add(&mut self, path: &Path) -> io::Result<&Asset> {
+        let path = fs::canonicalize(path)?;
+        if !self.files.contains_key(&path) {
+            let asset = Asset {
+                route: asset_route(&path, &fs::read(&path)?),
+                mime: mime_for(&path),
+            };
+            self.files.insert(path.clone(), asset);
+        }
+
+        Ok(&self.files[&path])
+    }
+
+    /// Canonical path of every embedded file with its asset
+    pub(crate) fn files(&self) -> impl Iterator<Item = (&PathBuf, &Asset)> {
+        self.files.iter()
+    }
+
+    pub(crate) fn routes(&self) -> impl Iterator<Item = &str> {
+        self.files.values().map(|asset| asset.route.as_str())
+    }
+
+    /// `ASSETS` table and `find_asset`. Contents go through `include_bytes!`
+    /// so editing an asset also rebuilds the crate.
+    pub(crate) fn generate(&self) -> TokenStream {
+        let mut entries: Vec<(&PathBuf, &Asset)> = self.files.iter().collect();
+        entries.sort_by(|a, b| a.1.route.cmp(&b.1.route));
+        entries.dedup_by(|b, a| a.1.route == b.1.route);
+
+        let entries = entries.iter().map(|(path, asset)| {
+            let (route, mime) = (&asset.route, asset.mime);
+            let path = path.display().to_string();
+            quote! { (#route, include_bytes!(#path), #mime) }
+        });
+
+        quote! {
+            /// Embedded static files: route, contents and MIME type, sorted
+            /// by route. Routes carry a content hash, so they can be cached
+            /// forever.
+            #[allow(dead_code)]
+            pub(crate) const ASSETS: &[(&str, &[u8], &str)] = &[
+                #(#entries),*
+            ];
+
+            /// Contents and MIME type of the asset at `route`
+            #[allow(dead_code)]
+            pub(crate) fn find_asset(route: &str) -> Option<(&'static [u8], &'static str)> {
+                let i = ASSETS.binary_search_by(|(r, _, _)| (*r).cmp(route)).ok()?;
+                Some((ASSETS[i].1, ASSETS[i].2))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::manifest_dir;
+
+    #[test]
+    fn routes_carry_a_content_hash() {
+        let a = asset_route(Path::new("img/logo.svg"), b"<svg/>");
+        let b = asset_route(Path::new("other/logo.svg"), b"<svg></svg>");
+
+        assert!(a.starts_with("/assets/logo.") && a.ends_with(".svg"));
+        assert_eq!(a.len(), "/assets/logo.12345678.svg".len());
+        assert_ne!(a, b);
+        assert_eq!(asset_route(Path::new("LICENSE"), b"x").len(), "/assets/LICENSE.12345678".len());
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+        assert_eq!(data_uri(Path::new("a.png"), b"foo"), "data:image/png;base64,Zm9v");
+    }
+
+    #[test]
+    fn mime_types_follow_extensions() {
+        assert_eq!(mime_for(Path::new("a.SVG")), "image/svg+xml");
+        assert_eq!(mime_for(Path::new("a.woff2")), "font/woff2");
+        assert_eq!(mime_for(Path::new("a")), "application/octet-stream");
+    }
+
+    #[test]
+    fn files_resolve_relative_to_the_source_only() {
+        let src = manifest_dir().join("../tests/assets/content/index.md");
+        let image = manifest_dir().join("../tests/assets/content/image.svg");
+
+        assert_eq!(resolve_file(&src, "image.svg"), Some(image.clone()));
+        assert_eq!(resolve_file(&src, "../content/image.svg"), Some(manifest_dir().join("../tests/assets/content/../content/image.svg")));
+        // Relative to the crate, not to the source
+        assert_eq!(resolve_file(&src, "../tests/assets/content/image.svg"), None);
+        assert_eq!(resolve_file(&src, "src/lib.rs"), None);
+        assert_eq!(resolve_file(&src, &image.display().to_string()), Some(image));
+        assert_eq!(resolve_file(&src, "missing.svg"), None);
+    }
+
+    #[test]
+    fn files_are_embedded_once() {
+        let image = manifest_dir().join("../tests/assets/content/image.svg");
+        let mut assets = Assets::default();
+        let route = assets.add(&image).unwrap().route.clone();
+        assets.add(&manifest_dir().join("../tests/assets/content/../content/image.svg")).unwrap();
+
+        assert_eq!(assets.routes().collect::<Vec<_>>(), vec![route.as_str()]);
+        assert_eq!(assets.files.values().next().unwrap().mime, "image/svg+xml");
+    }
+}
@@ -0,0 +1,396 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use serde_json::Value;
+
+/// Metadata value extracted from a document's YAML metadata block
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MetaValue {
+    String(String),
+    Bool(bool),
+    List(Vec<MetaValue>),
+    Map(Vec<(String, MetaValue)>),
+}
+
+impl ToTokens for MetaValue {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let code = match self {
+            MetaValue::String(s) => quote! { Meta::String(#s) },
+            MetaValue::Bool(b) => quote! { Meta::Bool(#b) },
+            MetaValue::List(items) => quote! { Meta::List(&[#(#items),*]) },
+            MetaValue::Map(entries) => {
+                let entries = entries.iter().map(|(k, v)| quote! { (#k, #v) });
+                quote! { Meta::Map(&[#(#entries),*]) }
+            }
+        };
+        tokens.extend(code);
+    }
+}
+
+impl MetaValue {
+    pub(crate) fn to_json(&self) -> Value {
+        match self {
+            MetaValue::String(s) => Value::String(s.clone()),
+            MetaValue::Bool(b) => Value::Bool(*b),
+            MetaValue::List(items) => Value::Array(items.iter().map(MetaValue::to_json).collect()),
+            MetaValue::Map(entries) => Value::Object(entries.iter().map(|(k, v)| (k.clone(), v.to_json())).collect()),
+        }
+    }
+}
+
+/// Section heading, in document order
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Heading {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
+}
+
+impl ToTokens for Heading {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Heading { level, id, text } = self;
+        tokens.extend(quote! { Heading { level: #level, id: #id, text: #text } });
+    }
+}
+
+/// The parts of a pandoc JSON AST the generator cares about
+#[derive(Debug)]
+pub(crate) struct Document {
+    pub meta: MetaValue,
+    pub headings: Vec<Heading>,
+    /// Plain text of the body, one paragraph per block
+    pub text: String,
+    /// Target of every `Link`, as written in the source
+    pub links: Vec<String>,
+}
+
+impl Document {
+    /// Parse the output of `pandoc -t json`
+    pub(crate) fn from_json(json: &str) -> Result<Self, String> {
+        let root: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid pandoc JSON: {}", e))?;
+
+        let meta = match root.get("meta") {
+            Some(Value::Object(map)) => MetaValue::Map(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), parse_meta(v)?)))
+                    .collect::<Result<Vec<_>, String>>()?,
+            ),
+            Some(_) => return Err("pandoc JSON `meta` is not an object".to_string()),
+            None => MetaValue::Map(Vec::new()),
+        };
+
+        let blocks = root.get("blocks").unwrap_or(&Value::Null);
+        let mut headings = Vec::new();
+        collect_headings(blocks, &mut headings);
+        let mut links = Vec::new();
+        collect_links(blocks, &mut links);
+
+        Ok(Self {
+            meta,
+            headings,
+            text: stringify_blocks(blocks),
+            links,
+        })
+    }
+}
+
+/// Gather link targets anywhere in the document, including notes and tables
+fn collect_links(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Object(node) => {
+            // [Attr, [Inline], [url, title]]
+            if node.get("t").and_then(Value::as_str) == Some("Link")
+                && let Some(url) = node.get("c").and_then(|c| c.get(2)).and_then(|t| t.get(0)).and_then(Value::as_str)
+            {
+                out.push(url.to_string());
+            }
+            node.values().for_each(|v| collect_links(v, out));
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_links(v, out)),
+        _ => {}
+    }
+}
+
+/// Gather `Header` blocks, looking into `Div`s that wrap sections
+fn collect_headings(blocks: &Value, out: &mut Vec<Heading>) {
+    for block in as_array(blocks) {
+        let content = block.get("c").unwrap_or(&Value::Null);
+
+        match block.get("t").and_then(Value::as_str).unwrap_or_default() {
+            // [level, [id, classes, attributes], inlines]
+            "Header" => out.push(Heading {
+                level: content.get(0).and_then(Value::as_u64).unwrap_or(1) as u8,
+                id: content
+                    .get(1)
+                    .and_then(|attr| attr.get(0))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                text: stringify_inlines(content.get(2).unwrap_or(&Value::Null)),
+            }),
+            "Div" => collect_headings(content.get(1).unwrap_or(&Value::Null), out),
+            _ => {}
+        }
+    }
+}
+
+fn parse_meta(value: &Value) -> Result<MetaValue, String> {
+    let tag = value
+        .get("t")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Meta value without tag: {}", value))?;
+    let content = value.get("c").unwrap_or(&Value::Null);
+
+    match tag {
+        "MetaString" => Ok(MetaValue::String(content.as_str().unwrap_or_default().to_string())),
+        "MetaBool" => Ok(MetaValue::Bool(content.as_bool().unwrap_or_default())),
+        "MetaInlines" => Ok(MetaValue::String(stringify_inlines(content))),
+        "MetaBlocks" => Ok(MetaValue::String(stringify_blocks(content))),
+        "MetaList" => Ok(MetaValue::List(
+            as_array(content)
+                .iter()
+                .map(parse_meta)
+                .collect::<Result<_, _>>()?,
+        )),
+        "MetaMap" => match content {
+            Value::Object(map) => Ok(MetaValue::Map(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), parse_meta(v)?)))
+                    .collect::<Result<_, String>>()?,
+            )),
+            _ => Err("MetaMap content is not an object".to_string()),
+        },
+        other => Err(format!("Unknown meta value type `{}`", other)),
+    }
+}
+
+fn as_array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Flatten a list of pandoc inlines into plain text
+pub(crate) fn stringify_inlines(inlines: &Value) -> String {
+    let mut out = String::new();
+    for inline in as_array(inlines) {
+        stringify_inline(inline, &mut out);
+    }
+    out
+}
+
+fn stringify_inline(inline: &Value, out: &mut String) {
+    let content = inline.get("c").unwrap_or(&Value::Null);
+
+    match inline.get("t").and_then(Value::as_str).unwrap_or_default() {
+        "Str" => out.push_str(content.as_str().unwrap_or_default()),
+        "Space" | "SoftBreak" | "LineBreak" => out.push(' '),
+        "Emph" | "Underline" | "Strong" | "Strikeout" | "Superscript" | "Subscript"
+        | "SmallCaps" => {
+            for i in as_array(content) {
+                stringify_inline(i, out);
+            }
+        }
+        "Quoted" => {
+            let quote = match content.get(0).and_then(|q| q.get("t")).and_then(Value::as_str) {
+                Some("SingleQuote") => '\'',
+                _ => '"',
+            };
+            out.push(quote);
+            out.push_str(&stringify_inlines(content.get(1).unwrap_or(&Value::Null)));
+            out.push(quote);
+        }
+        // [Attr, [Inline], Target], [[Citation], [Inline]] and [Attr, [Inline]]
+        "Link" | "Image" | "Span" | "Cite" => {
+            out.push_str(&stringify_inlines(content.get(1).unwrap_or(&Value::Null)))
+        }
+        "Code" | "Math" | "RawInline" => {
+            out.push_str(content.get(1).and_then(Value::as_str).unwrap_or_default())
+        }
+        _ => {}
+    }
+}
+
+/// Flatten a list of pandoc blocks into plain text, one paragraph per block
+pub(crate) fn stringify_blocks(blocks: &Value) -> String {
+    as_array(blocks)
+        .iter()
+        .map(|block| {
+            let content = block.get("c").unwrap_or(&Value::Null);
+            match block.get("t").and_then(Value::as_str).unwrap_or_default() {
+                "Plain" | "Para" => stringify_inlines(content),
+                "Header" => stringify_inlines(content.get(2).unwrap_or(&Value::Null)),
+                "CodeBlock" | "RawBlock" => {
+                    content.get(1).and_then(Value::as_str).unwrap_or_default().to_string()
+                }
+                "BlockQuote" => stringify_blocks(content),
+                "Div" => stringify_blocks(content.get(1).unwrap_or(&Value::Null)),
+                "BulletList" => stringify_list_items(content),
+                "OrderedList" => stringify_list_items(content.get(1).unwrap_or(&Value::Null)),
+                _ => String::new(),
+            }
+        })
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn stringify_list_items(items: &Value) -> String {
+    as_array(items)
+        .iter()
+        .map(stringify_blocks)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn str_inlines(words: &[&str]) -> Value {
+        let mut out = Vec::new();
+        for (i, w) in words.iter().enumerate() {
+            if i > 0 {
+                out.push(serde_json::json!({ "t": "Space" }));
+            }
+            out.push(serde_json::json!({ "t": "Str", "c": w }));
+        }
+        Value::Array(out)
+    }
+
+    #[test]
+    fn headings_are_collected_in_order() {
+        let json = serde_json::json!({
+            "meta": {},
+            "blocks": [
+                { "t": "Header", "c": [1, ["intro", [], []], str_inlines(&["Intro"])] },
+                { "t": "Para", "c": str_inlines(&["text"]) },
+                { "t": "Div", "c": [["", ["section"], []], [
+                    { "t": "Header", "c": [2, ["deep-dive", [], []], str_inlines(&["Deep", "dive"])] }
+                ]] }
+            ]
+        });
+        let doc = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            doc.headings,
+            vec![
+                Heading { level: 1, id: "intro".to_string(), text: "Intro".to_string() },
+                Heading { level: 2, id: "deep-dive".to_string(), text: "Deep dive".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn links_are_collected_from_nested_inlines() {
+        let link = |url: &str| serde_json::json!({ "t": "Link", "c": [["", [], []], str_inlines(&["see"]), [url, ""]] });
+        let json = serde_json::json!({
+            "meta": {},
+            "blocks": [
+                { "t": "Para", "c": [link("a.md"), { "t": "Emph", "c": [link("b.md#x")] }] },
+                { "t": "BulletList", "c": [[{ "t": "Plain", "c": [link("https://example.com")] }]] }
+            ]
+        });
+        let doc = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(doc.links, vec!["a.md", "b.md#x", "https://example.com"]);
+    }
+
+    #[test]
+    fn document_without_meta_has_empty_map() {
+        let doc = Document::from_json(r#"{"pandoc-api-version":[1,23],"meta":{},"blocks":[]}"#)
+            .unwrap();
+        assert_eq!(doc.meta, MetaValue::Map(vec![]));
+    }
+
+    #[test]
+    fn meta_inlines_are_stringified() {
+        let json = serde_json::json!({
+            "meta": {
+                "title": { "t": "MetaInlines", "c": str_inlines(&["Hello", "World"]) }
+            },
+            "blocks": []
+        });
+        let doc = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            doc.meta,
+            MetaValue::Map(vec![(
+                "title".to_string(),
+                MetaValue::String("Hello World".to_string())
+            )])
+        );
+    }
+
+    #[test]
+    fn meta_lists_bools_and_maps() {
+        let json = serde_json::json!({
+            "meta": {
+                "draft": { "t": "MetaBool", "c": true },
+                "tags": { "t": "MetaList", "c": [
+                    { "t": "MetaInlines", "c": str_inlines(&["rust"]) },
+                    { "t": "MetaString", "c": "pandoc" }
+                ]},
+                "author": { "t": "MetaMap", "c": {
+                    "name": { "t": "MetaInlines", "c": str_inlines(&["Jane"]) }
+                }}
+            },
+            "blocks": []
+        });
+        let doc = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            doc.meta,
+            MetaValue::Map(vec![
+                (
+                    "author".to_string(),
+                    MetaValue::Map(vec![(
+                        "name".to_string(),
+                        MetaValue::String("Jane".to_string())
+                    )])
+                ),
+                ("draft".to_string(), MetaValue::Bool(true)),
+                (
+                    "tags".to_string(),
+                    MetaValue::List(vec![
+                        MetaValue::String("rust".to_string()),
+                        MetaValue::String("pandoc".to_string())
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn meta_blocks_are_joined_by_paragraph() {
+        let json = serde_json::json!({
+            "meta": {
+                "abstract": { "t": "MetaBlocks", "c": [
+                    { "t": "Para", "c": str_inlines(&["First"]) },
+                    { "t": "Para", "c": str_inlines(&["Second"]) }
+                ]}
+            },
+            "blocks": []
+        });
+        let doc = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            doc.meta,
+            MetaValue::Map(vec![(
+                "abstract".to_string(),
+                MetaValue::String("First\n\nSecond".to_string())
+            )])
+        );
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        assert!(Document::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn meta_value_to_tokens() {
+        let meta = MetaValue::Map(vec![(
+            "tags".to_string(),
+            MetaValue::List(vec![MetaValue::Bool(false)]),
+        )]);
+        let code = quote! { #meta }.to_string();
+        assert_eq!(
+            code,
+            quote! { Meta::Map(&[("tags", Meta::List(&[Meta::Bool(false)]))]) }.to_string()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use proc_macro2::Span;
+use syn::{Ident, parse::Parse};
+
+use crate::{discover, options::Options, sidecar::SourceOptions};
+
+/// What converts source files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The `pandoc` binary
+    Pandoc,
+    /// pulldown-cmark, in process, behind the `native` feature
+    Native,
+    /// Deterministic stand-in for tests, see `mock`
+    Mock,
+}
+
+impl Default for Backend {
+    /// Pandoc, unless the `native-default` feature is on
+    fn default() -> Self {
+        if cfg!(feature = "native-default") { Backend::Native } else { Backend::Pandoc }
+    }
+}
+
+impl Parse for Backend {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let ident = input.parse::<Ident>()?;
+        match ident.to_string().to_lowercase().as_str() {
+            "pandoc" => Ok(Backend::Pandoc),
+            "mock" => Ok(Backend::Mock),
+            "native" if cfg!(feature = "native") => Ok(Backend::Native),
+            "native" => Err(syn::Error::new(
+                ident.span(),
+                "backend = Native requires the `native` feature of pandoc-generator",
+            )),
+            other => Err(syn::Error::new(
+                ident.span(),
+                format!("unknown backend `{}`, expected Pandoc, Native or Mock", other),
+            )),
+        }
+    }
+}
+
+/// The converter an invocation runs, once checked against its options
+pub(crate) struct Toolchain {
+    /// Version string conversions are cached under
+    pub version: String,
+    /// Directory of the `pandoc` executable, `None` for PATH
+    pub pandoc_dir: Option<PathBuf>,
+}
+
+impl Backend {
+    /// Check that the backend is available and supports every requested
+    /// format and extension, before converting anything
+    pub(crate) fn probe(self, options: &Options) -> syn::Result<Toolchain> {
+        match self {
+            Backend::Pandoc => {
+                let pandoc = discover::probe(options)?;
+                Ok(Toolchain {
+                    version: pandoc.version,
+                    pandoc_dir: pandoc.dir,
+                })
+            }
+            #[cfg(feature = "native")]
+            Backend::Native => {
+                crate::native::check(options).map_err(|e| syn::Error::new(Span::call_site(), e))?;
+                Ok(Toolchain {
+                    version: crate::native::VERSION.to_string(),
+                    pandoc_dir: None,
+                })
+            }
+            Backend::Mock => Ok(Toolchain {
+                version: crate::mock::VERSION.to_string(),
+                pandoc_dir: None,
+            }),
+            #[cfg(not(feature = "native"))]
+            Backend::Native => Err(syn::Error::new(
+                Span::call_site(),
+                "backend = Native requires the `native` feature of pandoc-generator",
+            )),
+        }
+    }
+
+    /// Check the options a source got from an options file, which `probe`
+    /// did not see, with the converter `probe` found
+    pub(crate) fn check_source(self, toolchain: &Toolchain, options: &SourceOptions) -> Result<(), String> {
+        match self {
+            Backend::Pandoc => discover::check_capabilities(
+                toolchain.pandoc_dir.as_deref(),
+                &toolchain.version,
+                options.input_format.as_ref(),
+                &[],
+            ),
+            #[cfg(feature = "native")]
+            Backend::Native => crate::native::check_source(options),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use pandoc::{InputFormat, MarkdownExtension};
+
+    /// Directory removed when the test ends, even if it fails
+    #[cfg(unix)]
+    struct TempDir(PathBuf);
+
+    #[cfg(unix)]
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A `pandoc` that only lists a few formats and extensions
+    #[cfg(unix)]
+    fn stub_pandoc() -> TempDir {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir(std::env::temp_dir().join(format!("pandoc-generator-stub-{}", std::process::id())));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        let script = "#!/bin/sh\ncase \"$1\" in\n  --list-input-formats) printf 'markdown\\ncommonmark\\n' ;;\n  --list-output-formats) printf 'html\\n' ;;\n  --list-extensions=*) printf '+smart\\n' ;;\nesac\n";
+        let path = dir.0.join("pandoc");
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        dir
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn source_options_are_checked_against_pandoc() {
+        let dir = stub_pandoc();
+        let toolchain = Toolchain {
+            version: "pandoc 3.1".to_string(),
+            pandoc_dir: Some(dir.0.clone()),
+        };
+        let source = |format, extensions| SourceOptions {
+            input_format: Some((format, extensions)),
+            pandoc_options: Vec::new(),
+        };
+
+        assert_eq!(
+            Backend::Pandoc.check_source(&toolchain, &source(InputFormat::Commonmark, vec![MarkdownExtension::Smart])),
+            Ok(())
+        );
+        assert_eq!(
+            Backend::Pandoc.check_source(&toolchain, &source(InputFormat::Other("nosuchformat".to_string()), Vec::new())),
+            Err("format `nosuchformat` is not supported by pandoc 3.1".to_string())
+        );
+        assert_eq!(
+            Backend::Pandoc.check_source(&toolchain, &source(InputFormat::Markdown, vec![MarkdownExtension::Footnotes])),
+            Err("extension `footnotes` is not supported by pandoc 3.1 for `markdown`".to_string())
+        );
+        let unsupported = source(InputFormat::Other("nosuchformat".to_string()), Vec::new());
+        assert_eq!(Backend::Mock.check_source(&toolchain, &unsupported), Ok(()));
+    }
+
+    #[test]
+    fn backends_are_parsed() {
+        assert_eq!(syn::parse_str::<Backend>("Pandoc").unwrap(), Backend::Pandoc);
+        assert_eq!(syn::parse_str::<Backend>("Mock").unwrap(), Backend::Mock);
+        assert!(syn::parse_str::<Backend>("lowdown").is_err());
+        assert_eq!(syn::parse_str::<Backend>("native").is_ok(), cfg!(feature = "native"));
+    }
+}
@@ -0,0 +1,532 @@
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use pandoc::{InputFormat, MarkdownExtension, OutputFormat, PandocOption};
+use proc_macro2::{Span, TokenStream};
+use syn::Ident;
+
+use crate::{
+    backend::Backend,
+    check::LinkCheck,
+    discover::VersionReq,
+    expand, export,
+    export::Export,
+    options::{Element, Options},
+    tree::FsTree,
+};
+
+/// Everything that went wrong, the same messages the macro reports as
+/// compile errors
+#[derive(Debug)]
+pub struct Error(syn::Error);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self.0.clone().into_iter().map(|e| e.to_string()).collect();
+        f.write_str(&messages.join("\n"))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<syn::Error> for Error {
+    fn from(err: syn::Error) -> Self {
+        Self(err)
+    }
+}
+
+/// Generated module, and the trees it was built from
+pub struct Generated {
+    tokens: TokenStream,
+    trees: Vec<FsTree>,
+    config: Option<PathBuf>,
+}
+
+impl Generated {
+    pub(crate) fn new(tokens: TokenStream, trees: Vec<FsTree>, config: Option<PathBuf>) -> Self {
+        Self { tokens, trees, config }
+    }
+
+    /// The module, as `pandoc_compile_html!` would expand to
+    pub fn tokens(&self) -> &TokenStream {
+        &self.tokens
+    }
+
+    pub fn into_tokens(self) -> TokenStream {
+        self.tokens
+    }
+
+    pub fn trees(&self) -> &[FsTree] {
+        &self.trees
+    }
+
+    /// Directories scanned and files read; changing any of them changes the
+    /// output
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        self.trees
+            .iter()
+            .flat_map(|t| {
+                let files = t.get_all_src_files().into_iter().chain(t.assets.iter().cloned());
+                t.dirs.iter().cloned().chain(files).chain(t.options_files.iter().cloned())
+            })
+            .chain(self.config.iter().cloned())
+            .map(|path| fs::canonicalize(&path).unwrap_or(path))
+            .collect()
+    }
+
+    /// Write the module to `path`, for `include!`
+    pub fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.tokens.to_string())
+    }
+
+    /// Write the module to `$OUT_DIR/file_name` from a build script, and
+    /// tell cargo to rerun it when a dependency changes
+    pub fn write_to_out_dir(&self, file_name: &str) -> io::Result<PathBuf> {
+        let out_dir = env::var_os("OUT_DIR")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set, call this from build.rs"))?;
+        let path = Path::new(&out_dir).join(file_name);
+        self.write_to(&path)?;
+
+        for dependency in self.dependencies() {
+            println!("cargo:rerun-if-changed={}", dependency.display());
+        }
+        for var in ["PANDOC_GENERATOR_PANDOC", "PANDOC_GENERATOR_CACHE_DIR"] {
+            println!("cargo:rerun-if-env-changed={}", var);
+        }
+
+        Ok(path)
+    }
+}
+
+/// Runs the same pipeline as `pandoc_compile_html!`, outside a macro.
+/// Arguments mirror the macro's; unset ones keep the macro's defaults.
+///
+/// ```no_run
+/// use pandoc_generator_core::{Builder, InputFormat, MarkdownExtension, OutputFormat};
+///
+/// Builder::new()
+///     .mod_name("docs")
+///     .tree_name("DOCS")
+///     .source("docs", "/docs")
+///     .input_format(InputFormat::Markdown, vec![MarkdownExtension::Smart])
+///     .output_format(OutputFormat::Html5, vec![])
+///     .nproc(8)
+///     .build()
+///     .unwrap()
+///     .write_to_out_dir("docs.rs")
+///     .unwrap();
+/// ```
+pub struct Builder {
+    options: Options,
+    mod_name: Option<String>,
+    tree_name: Option<String>,
+    metadata: Option<String>,
+    feed: Option<String>,
+    pandoc_version: Option<String>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    /// Generates `mod content` with a `CONTENT` tree unless renamed
+    pub fn new() -> Self {
+        Self {
+            options: Options::new(
+                Ident::new("content", Span::call_site()),
+                Ident::new("CONTENT", Span::call_site()),
+            ),
+            mod_name: None,
+            tree_name: None,
+            metadata: None,
+            feed: None,
+            pandoc_version: None,
+        }
+    }
+
+    pub fn mod_name(mut self, name: &str) -> Self {
+        self.mod_name = Some(name.to_string());
+        self
+    }
+
+    pub fn tree_name(mut self, name: &str) -> Self {
+        self.tree_name = Some(name.to_string());
+        self
+    }
+
+    /// Convert the file or directory at `path`, routed under `route`
+    pub fn source(mut self, path: impl Into<String>, route: impl Into<String>) -> Self {
+        self.options.content.push(Element::CompileFromPath {
+            path: path.into(),
+            route: Some(route.into()),
+            span: Span::call_site(),
+        });
+        self
+    }
+
+    /// Convert the file or directory at `path` without giving it a route
+    pub fn unrouted_source(mut self, path: impl Into<String>) -> Self {
+        self.options.content.push(Element::CompileFromPath {
+            path: path.into(),
+            route: None,
+            span: Span::call_site(),
+        });
+        self
+    }
+
+    pub fn source_ext(mut self, ext: impl Into<String>) -> Self {
+        self.options.source_ext = Some(ext.into());
+        self
+    }
+
+    pub fn input_format(mut self, format: InputFormat, extensions: Vec<MarkdownExtension>) -> Self {
+        self.options.input_format = Some((format, extensions));
+        self
+    }
+
+    /// Add an output format, the first one being the primary
+    pub fn output_format(mut self, format: OutputFormat, extensions: Vec<MarkdownExtension>) -> Self {
+        self.options.output_format.push((format, extensions));
+        self
+    }
+
+    pub fn pandoc_option(mut self, option: PandocOption) -> Self {
+        self.options.pandoc_options.push(option);
+        self
+    }
+
+    pub fn nproc(mut self, nproc: usize) -> Self {
+        self.options.nproc = nproc;
+        self
+    }
+
+    /// Typed front matter, in the macro's syntax: `{ title: String }`
+    pub fn metadata(mut self, schema: &str) -> Self {
+        self.metadata = Some(schema.to_string());
+        self
+    }
+
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.options.cache = cache;
+        self
+    }
+
+    /// Directory relative paths and `base` resolve against, the crate
+    /// being built by default
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.options.root = root.into();
+        self
+    }
+
+    pub fn base(mut self, base: impl Into<String>) -> Self {
+        self.options.base = Some(base.into());
+        self
+    }
+
+    pub fn toc_depth(mut self, depth: u8) -> Self {
+        self.options.toc_depth = depth;
+        self
+    }
+
+    pub fn search_index(mut self, enabled: bool) -> Self {
+        self.options.search_index = enabled;
+        self
+    }
+
+    pub fn search_json(mut self, enabled: bool) -> Self {
+        self.options.search_json = enabled;
+        self
+    }
+
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.options.base_url = Some(url.into());
+        self
+    }
+
+    pub fn sitemap(mut self, enabled: bool) -> Self {
+        self.options.sitemap = enabled;
+        self
+    }
+
+    /// Atom feed, in the macro's syntax: `{ title: "News", from: ["news"] }`
+    pub fn feed(mut self, feed: &str) -> Self {
+        self.feed = Some(feed.to_string());
+        self
+    }
+
+    pub fn check_links(mut self, level: LinkCheck) -> Self {
+        self.options.check_links = level;
+        self
+    }
+
+    /// Extensions of files embedded as assets
+    pub fn assets(mut self, extensions: &[&str]) -> Self {
+        self.options.assets = Some(extensions.iter().map(|e| e.trim_start_matches('.').to_string()).collect());
+        self
+    }
+
+    pub fn inline_assets_below(mut self, bytes: u64) -> Self {
+        self.options.inline_assets_below = Some(bytes);
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.options.backend = backend;
+        self
+    }
+
+    pub fn pandoc_path(mut self, path: impl Into<String>) -> Self {
+        self.options.pandoc_path = Some((path.into(), Span::call_site()));
+        self
+    }
+
+    /// Required pandoc version, e.g. `">=3.1"`
+    pub fn pandoc_version(mut self, requirement: &str) -> Self {
+        self.pandoc_version = Some(requirement.to_string());
+        self
+    }
+
+    /// Check the arguments, convert every source and generate the module
+    pub fn build(self) -> Result<Generated, Error> {
+        Ok(expand(&self.into_options()?)?)
+    }
+
+    /// Like `build`, but render the pages to files laid out by route
+    pub fn export(self) -> Result<Export, Error> {
+        Ok(export(&self.into_options()?)?)
+    }
+
+    fn into_options(self) -> Result<Options, Error> {
+        let mut options = self.options;
+        let call_site = |e: String| syn::Error::new(Span::call_site(), e);
+
+        if options.content.is_empty() {
+            return Err(call_site("no sources, add one with `source`".to_string()).into());
+        }
+        let ident = |argument: &str, name: String| {
+            syn::parse_str::<Ident>(&name).map_err(|_| call_site(format!("{} `{}` is not a valid identifier", argument, name)))
+        };
+        if let Some(name) = self.mod_name {
+            options.mod_name = ident("mod_name", name)?;
+        }
+        if let Some(name) = self.tree_name {
+            options.tree_name = ident("tree_name", name)?;
+        }
+        if let Some(schema) = self.metadata {
+            options.metadata = Some(syn::parse_str(&schema)?);
+        }
+        if let Some(feed) = self.feed {
+            options.feed = Some(syn::parse_str(&feed)?);
+        }
+        if let Some(req) = self.pandoc_version {
+            options.pandoc_version = Some((VersionReq::parse(&req).map_err(call_site)?, Span::call_site()));
+        }
+        if options.base_url.is_none() && (options.sitemap || options.feed.is_some()) {
+            return Err(call_site("base_url is required by sitemap and feed".to_string()).into());
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets::data_uri, paths::manifest_dir};
+
+    fn assets() -> String {
+        manifest_dir().join("../tests/assets").display().to_string()
+    }
+
+    #[test]
+    fn builds_with_the_mock_backend() {
+        let generated = Builder::new()
+            .mod_name("docs")
+            .source(format!("{}/mod_test", assets()), "/")
+            .output_format(OutputFormat::Html5, Vec::new())
+            .backend(Backend::Mock)
+            .cache(false)
+            .nproc(2)
+            .build()
+            .unwrap();
+
+        let code = generated.tokens().to_string();
+        assert!(code.contains("pub (crate) mod docs"));
+        assert!(code.contains("CONTENT"));
+        assert!(code.contains("<!-- mock html5 -->\\n## Page 1"));
+
+        assert_eq!(generated.trees().len(), 1);
+        let dependencies = generated.dependencies();
+        assert!(dependencies.iter().any(|p| p.ends_with("mod_test/about.md")));
+        assert!(dependencies.iter().any(|p| p.ends_with("mod_test/01-section")));
+    }
+
+    #[test]
+    fn errors_are_collected() {
+        let err = Builder::new()
+            .source("does/not/exist", "/a")
+            .source("also/missing", "/b")
+            .backend(Backend::Mock)
+            .build()
+            .err()
+            .unwrap();
+
+        let message = err.to_string();
+        assert_eq!(message.lines().count(), 2, "{}", message);
+        assert!(message.contains("does/not/exist"));
+    }
+
+    #[test]
+    fn arguments_are_validated() {
+        let no_sources = Builder::new().backend(Backend::Mock).build().err().unwrap();
+        assert!(no_sources.to_string().contains("no sources"));
+
+        let feed = Builder::new().source("x", "/").feed("{ title: \"News\" }").build().err().unwrap();
+        assert_eq!(feed.to_string(), "base_url is required by sitemap and feed");
+
+        let version = Builder::new().source("x", "/").pandoc_version("newest").build().err().unwrap();
+        assert!(version.to_string().contains("invalid pandoc version requirement"));
+
+        for name in ["my-docs", "1st", "fn"] {
+            let err = Builder::new().source("x", "/").mod_name(name).build().err().unwrap();
+            assert_eq!(err.to_string(), format!("mod_name `{}` is not a valid identifier", name));
+        }
+        let tree = Builder::new().source("x", "/").tree_name("").build().err().unwrap();
+        assert_eq!(tree.to_string(), "tree_name `` is not a valid identifier");
+    }
+
+    #[test]
+    fn pages_are_exported_by_route() {
+        let export = Builder::new()
+            .source(format!("{}/toc/guide.md", assets()), "/guide")
+            .source(format!("{}/toc/guide.md", assets()), "/guide/")
+            .unrouted_source(format!("{}/mod_test/about.md", assets()))
+            .output_format(OutputFormat::Html5, Vec::new())
+            .output_format(OutputFormat::Other("revealjs".to_string()), Vec::new())
+            .backend(Backend::Mock)
+            .cache(false)
+            .export()
+            .unwrap();
+
+        let files: Vec<&str> = export.files().map(|(path, _)| path).collect();
+        assert_eq!(files, ["guide/index.html", "guide/index.revealjs.html"]);
+
+        // The first page keeps a duplicated route, unrouted pages get no files
+        let elements = &export.manifest()["tree"]["elements"];
+        assert_eq!(elements[0]["outputs"]["revealjs"], "guide/index.revealjs.html");
+        assert_eq!(elements[1]["outputs"], serde_json::json!({}));
+        assert_eq!(elements[2]["route"], serde_json::Value::Null);
+        assert_eq!(elements[2]["order"], 2);
+    }
+
+    #[test]
+    fn sibling_routes_are_numbered() {
+        let generated = Builder::new()
+            .source(format!("{}/mod_test", assets()), "/")
+            .backend(Backend::Mock)
+            .cache(false)
+            .build()
+            .unwrap();
+
+        // `01-section` and `02-section` both have the slug `section`
+        let code = generated.tokens().to_string();
+        assert!(code.contains("(\"/section\" , & [0usize , 0usize])"), "{}", code);
+        assert!(code.contains("(\"/section-2\" , & [0usize , 1usize])"), "{}", code);
+    }
+
+    #[test]
+    fn broken_links_are_reported_by_level() {
+        let check = |level| {
+            Builder::new()
+                .source(format!("{}/broken_links", assets()), "/")
+                .backend(Backend::Mock)
+                .cache(false)
+                .check_links(level)
+                .build()
+        };
+
+        let err = check(LinkCheck::Deny).err().unwrap();
+        assert!(err.to_string().ends_with("index.md: broken link `missing.html`"), "{}", err);
+
+        let code = check(LinkCheck::Warn).unwrap().tokens().to_string();
+        assert!(code.contains("# [deprecated (note = "));
+        assert!(code.contains("const LINK_WARNING_0 : () = ()"));
+        assert!(code.contains("const _ : () = LINK_WARNING_0"));
+        assert!(code.contains("index.md: broken link `missing.html`"));
+
+        let code = check(LinkCheck::Allow).unwrap().tokens().to_string();
+        assert!(!code.contains("LINK_WARNING"));
+    }
+
+    #[test]
+    fn relative_sources_resolve_against_the_root() {
+        let generated = Builder::new()
+            .root(manifest_dir().join(".."))
+            .source("tests/assets/toc/guide.md", "/guide")
+            .backend(Backend::Mock)
+            .cache(false)
+            .build()
+            .unwrap();
+
+        let dependencies = generated.dependencies();
+        assert!(dependencies.iter().any(|p| p.ends_with("tests/assets/toc/guide.md")));
+    }
+
+    #[test]
+    fn only_files_next_to_the_source_are_embedded() {
+        struct TempDir(PathBuf);
+        impl Drop for TempDir {
+            fn drop(&mut self) {
+                let _ = fs::remove_dir_all(&self.0);
+            }
+        }
+
+        // `logo.png` is at the crate root, not next to the page
+        let dir = TempDir(env::temp_dir().join(format!("pandoc-generator-embed-{}", std::process::id())));
+        fs::create_dir_all(dir.0.join("content")).unwrap();
+        fs::write(dir.0.join("logo.png"), "crate root").unwrap();
+        fs::write(dir.0.join("content/icon.png"), "next to the page").unwrap();
+        fs::write(dir.0.join("content/page.md"), "<img src=\"logo.png\"> <img src=\"icon.png\">\n").unwrap();
+
+        let build = |builder: Builder| {
+            builder
+                .root(&dir.0)
+                .source("content", "/")
+                .backend(Backend::Mock)
+                .cache(false)
+                .build()
+                .unwrap()
+                .tokens()
+                .to_string()
+        };
+
+        let inlined = build(Builder::new().inline_assets_below(8192));
+        assert!(inlined.contains(&format!("src=\\\"{}\\\"", data_uri(Path::new("icon.png"), b"next to the page"))));
+        assert!(inlined.contains("src=\\\"logo.png\\\""), "{}", inlined);
+
+        let embedded = build(Builder::new().assets(&[]));
+        assert!(embedded.contains("/assets/icon."));
+        assert!(!embedded.contains("/assets/logo."), "{}", embedded);
+        assert!(embedded.contains("src=\\\"logo.png\\\""));
+    }
+
+    #[test]
+    fn module_is_written() {
+        let path = env::temp_dir().join(format!("pandoc-generator-builder-{}.rs", std::process::id()));
+        let generated = Builder::new()
+            .source(format!("{}/toc/guide.md", assets()), "/guide")
+            .backend(Backend::Mock)
+            .cache(false)
+            .build()
+            .unwrap();
+
+        generated.write_to(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), generated.tokens().to_string());
+        let _ = fs::remove_file(&path);
+    }
+}
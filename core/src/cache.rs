@@ -0,0 +1,278 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use pandoc::{PandocOption, PandocOutput};
+use sha2::{Digest, Sha256};
+
+use crate::sidecar::SourceOptions;
+
+const CACHE_DIR_ENV: &str = "PANDOC_GENERATOR_CACHE_DIR";
+const CACHE_DIR_NAME: &str = "pandoc-generator-cache";
+
+/// On-disk cache of pandoc conversions, keyed by a hash of everything that
+/// can influence the result.
+pub(crate) struct Cache {
+    dir: PathBuf,
+    fingerprint: String,
+}
+
+impl Cache {
+    /// Open the cache for a macro invocation converting with the backend
+    /// at `version`, for the crate at `root`
+    pub(crate) fn open(version: &str, root: &Path) -> io::Result<Self> {
+        let dir = default_dir(root);
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            fingerprint: fingerprint(version),
+        })
+    }
+
+    /// Cache key of a source file rendered to `target` (an output format or
+    /// the AST) with its options. The path is part of it since relative
+    /// links and images are resolved against it.
+    pub(crate) fn key(&self, src: &Path, options: &SourceOptions, target: &str) -> io::Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.fingerprint.as_bytes());
+        hasher.update(options_fingerprint(options).as_bytes());
+        hasher.update(fs::canonicalize(src)?.to_string_lossy().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(target.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(fs::read(src)?);
+        Ok(hex(&hasher.finalize()))
+    }
+
+    pub(crate) fn get_output(&self, key: &str) -> Option<PandocOutput> {
+        let output = fs::read(self.dir.join(format!("{}.out", key))).ok()?;
+
+        match output.split_first() {
+            Some((b'T', text)) => Some(PandocOutput::ToBuffer(String::from_utf8(text.to_vec()).ok()?)),
+            Some((b'B', bytes)) => Some(PandocOutput::ToBufferRaw(bytes.to_vec())),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_ast(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.dir.join(format!("{}.json", key))).ok()
+    }
+
+    pub(crate) fn put_ast(&self, key: &str, ast: &str) -> io::Result<()> {
+        write_atomic(&self.dir.join(format!("{}.json", key)), ast.as_bytes())
+    }
+
+    pub(crate) fn put_output(&self, key: &str, output: &PandocOutput) -> io::Result<()> {
+        let mut data = Vec::new();
+        match output {
+            PandocOutput::ToBuffer(text) => {
+                data.push(b'T');
+                data.extend_from_slice(text.as_bytes());
+            }
+            PandocOutput::ToBufferRaw(bytes) => {
+                data.push(b'B');
+                data.extend_from_slice(bytes);
+            }
+            PandocOutput::ToFile(_) => return Ok(()),
+        }
+
+        write_atomic(&self.dir.join(format!("{}.out", key)), &data)
+    }
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
+
+/// `$PANDOC_GENERATOR_CACHE_DIR`, or a directory inside cargo's target dir
+fn default_dir(root: &Path) -> PathBuf {
+    if let Some(dir) = env::var_os(CACHE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+
+    let target = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| workspace_target(root));
+
+    target.join(CACHE_DIR_NAME)
+}
+
+/// `target` directory of the workspace holding the crate at `dir`, which
+/// is `dir/target` for a crate outside any workspace
+fn workspace_target(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| fs::read_to_string(dir.join("Cargo.toml")).is_ok_and(|manifest| manifest.contains("[workspace]")))
+        .unwrap_or(dir)
+        .join("target")
+}
+
+/// Everything shared by the invocation's conversions that affects them
+fn fingerprint(pandoc_version: &str) -> String {
+    format!("{}\n{}\n", env!("CARGO_PKG_VERSION"), pandoc_version)
+}
+
+/// The options of one source, which directories can change
+fn options_fingerprint(options: &SourceOptions) -> String {
+    let mut out = format!("{:?}\n{:?}\n", options.input_format, options.pandoc_options);
+
+    // Templates, filters and the like are inputs too
+    for path in option_files(&options.pandoc_options) {
+        if let Ok(data) = fs::read(path) {
+            out.push_str(&hex(&Sha256::digest(&data)));
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+fn option_files(options: &[PandocOption]) -> Vec<&PathBuf> {
+    options
+        .iter()
+        .filter_map(|opt| match opt {
+            PandocOption::Defaults(p)
+            | PandocOption::Filter(p)
+            | PandocOption::LuaFilter(p)
+            | PandocOption::Template(p)
+            | PandocOption::IncludeInHeader(p)
+            | PandocOption::IncludeBeforeBody(p)
+            | PandocOption::IncludeAfterBody(p)
+            | PandocOption::Bibliography(p)
+            | PandocOption::Csl(p)
+            | PandocOption::CitationAbbreviations(p)
+            | PandocOption::ReferenceDoc(p) => Some(p),
+            _ => None,
+        })
+        .collect()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(prefix: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "{}_{}",
+            prefix,
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&path).expect("failed to create temp dir");
+        path
+    }
+
+    fn options(section: &str) -> SourceOptions {
+        let src = format!(
+            "mod_name = m, tree_name = T, content = [ compile_from_path(path: \"content\") ], {}",
+            section
+        );
+        syn::parse_str::<crate::options::Options>(&src)
+            .expect("failed to parse options")
+            .source_options()
+    }
+
+    fn cache(dir: &Path) -> Cache {
+        Cache {
+            dir: dir.to_path_buf(),
+            fingerprint: fingerprint("pandoc 3.1"),
+        }
+    }
+
+    #[test]
+    fn key_changes_with_content_target_and_options() {
+        let dir = temp_dir("cache_key");
+        let src = dir.join("page.md");
+        fs::write(&src, "# A").unwrap();
+
+        let cache = cache(&dir);
+        let plain = options("input_format = Markdown");
+        let smart = options("input_format = Markdown[Smart]");
+        let first = cache.key(&src, &plain, "html").unwrap();
+
+        assert_eq!(first, cache.key(&src, &plain, "html").unwrap());
+        assert_ne!(first, cache.key(&src, &plain, "latex").unwrap());
+        assert_ne!(first, cache.key(&src, &smart, "html").unwrap());
+
+        fs::write(&src, "# B").unwrap();
+        assert_ne!(first, cache.key(&src, &plain, "html").unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn identical_sources_in_different_directories_get_different_keys() {
+        let dir = temp_dir("cache_key_path");
+        let cache = cache(&dir);
+        let options = options("output_format = Html");
+
+        let mut keys = Vec::new();
+        for section in ["guide", "reference"] {
+            fs::create_dir_all(dir.join(section)).unwrap();
+            let src = dir.join(section).join("index.md");
+            fs::write(&src, "# Overview\n\n![diagram](diagram.svg)\n").unwrap();
+            keys.push(cache.key(&src, &options, "html").unwrap());
+        }
+        assert_ne!(keys[0], keys[1]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn member_crates_share_the_workspace_target_dir() {
+        let dir = temp_dir("cache_workspace");
+        let member = dir.join("core");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = [\"core\"]\n").unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"core\"\n").unwrap();
+
+        assert_eq!(workspace_target(&member), dir.join("target"));
+        assert_eq!(workspace_target(&dir), dir.join("target"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn key_changes_with_pandoc_version() {
+        assert_ne!(fingerprint("pandoc 3.1"), fingerprint("pandoc 3.2"));
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let dir = temp_dir("cache_round_trip");
+        let cache = cache(&dir);
+
+        assert!(cache.get_output("missing").is_none());
+        assert!(cache.get_ast("missing").is_none());
+
+        cache
+            .put_output("text", &PandocOutput::ToBuffer("<p>hi</p>".to_string()))
+            .unwrap();
+        assert!(matches!(
+            cache.get_output("text"),
+            Some(PandocOutput::ToBuffer(s)) if s == "<p>hi</p>"
+        ));
+
+        cache
+            .put_output("bin", &PandocOutput::ToBufferRaw(vec![0, 159, 146]))
+            .unwrap();
+        assert!(matches!(
+            cache.get_output("bin"),
+            Some(PandocOutput::ToBufferRaw(b)) if b == vec![0, 159, 146]
+        ));
+
+        cache.put_ast("ast", "{}").unwrap();
+        assert_eq!(cache.get_ast("ast").as_deref(), Some("{}"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::HashSet, path::Path};
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote_spanned};
+use syn::{Ident, parse::Parse};
+
+use crate::{
+    assets::resolve_file,
+    links::{has_scheme, percent_decode},
+};
+
+/// What to do about local links and assets that point nowhere
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkCheck {
+    Deny,
+    Warn,
+    Allow,
+}
+
+impl Parse for LinkCheck {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let ident = input.parse::<Ident>()?;
+        match ident.to_string().to_lowercase().as_str() {
+            "deny" => Ok(LinkCheck::Deny),
+            "warn" => Ok(LinkCheck::Warn),
+            "allow" => Ok(LinkCheck::Allow),
+            other => Err(syn::Error::new(
+                ident.span(),
+                format!("unknown check_links level `{}`, expected deny, warn or allow", other),
+            )),
+        }
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// A local `href` or `src` target in rendered HTML
+#[derive(Debug, PartialEq)]
+pub(crate) struct LocalUrl {
+    /// Lowercase name of the element carrying the attribute
+    pub tag: String,
+    pub attribute: &'static str,
+    /// Attribute value as written, HTML entities decoded
+    pub raw: String,
+    /// Percent-decoded path, without query or fragment
+    pub path: String,
+}
+
+/// `href` and `src` attribute values of rendered HTML that point at local
+/// paths
+pub(crate) fn local_urls(html: &str) -> Vec<LocalUrl> {
+    let mut urls = Vec::new();
+
+    for attribute in ["href", "src"] {
+        let pattern = format!(" {}=\"", attribute);
+        let mut offset = 0;
+        while let Some(start) = html[offset..].find(&pattern) {
+            let value_start = offset + start + pattern.len();
+            let Some(end) = html[value_start..].find('"') else {
+                break;
+            };
+            offset = value_start + end;
+
+            let tag = html[..value_start]
+                .rfind('<')
+                .map(|i| &html[i + 1..value_start])
+                .and_then(|element| element.split_whitespace().next())
+                .unwrap_or_default()
+                .to_lowercase();
+            let raw = unescape(&html[value_start..offset]);
+            let path = raw.split(['#', '?']).next().unwrap_or_default();
+            if !path.is_empty() && !raw.starts_with("//") && !has_scheme(&raw) {
+                let path = percent_decode(path);
+                urls.push(LocalUrl { tag, attribute, raw, path });
+            }
+        }
+    }
+
+    urls
+}
+
+/// Resolve a relative URL against the route of the page containing it
+fn join_route(route: &str, url: &str) -> String {
+    let mut segments: Vec<&str> = route.split('/').filter(|s| !s.is_empty()).collect();
+    // The page itself is the last segment
+    segments.pop();
+
+    for segment in url.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Check that `url`, found in the page rendered from `src` at `route`,
+/// is a route or an existing file
+pub(crate) fn check_url(src: &Path, route: Option<&str>, url: &str, routes: &HashSet<String>) -> bool {
+    let trimmed = match url.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+
+    if url.starts_with('/') && routes.contains(trimmed) {
+        return true;
+    }
+
+    resolve_file(src, url).is_some()
+        || (!url.starts_with('/') && route.is_some_and(|route| routes.contains(&join_route(route, trimmed))))
+}
+
+/// Compiler warning pointing at `span`: a deprecated constant and a use of
+/// it, which is the only way to emit warnings from a stable proc macro
+pub(crate) fn warning(index: usize, message: &str, span: Span) -> TokenStream {
+    let name = format_ident!("LINK_WARNING_{}", index, span = span);
+
+    quote_spanned! {span=>
+        #[deprecated(note = #message)]
+        #[allow(dead_code)]
+        const #name: () = ();
+        const _: () = #name;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::manifest_dir;
+
+    #[test]
+    fn levels_are_parsed() {
+        assert_eq!(syn::parse_str::<LinkCheck>("deny").unwrap(), LinkCheck::Deny);
+        assert_eq!(syn::parse_str::<LinkCheck>("Warn").unwrap(), LinkCheck::Warn);
+        assert!(syn::parse_str::<LinkCheck>("ignore").is_err());
+    }
+
+    #[test]
+    fn only_local_urls_are_extracted() {
+        let html = r##"<a href="https://x.org/a">a</a> <a href="#top">t</a> <img src="img/my%20pic.png?v=1" />
+            <a href="../guide#setup">g</a> <a href="//cdn.x/y.js">c</a> <a href="mailto:a@b.c">m</a>"##;
+        let urls: Vec<_> = local_urls(html).into_iter().map(|url| (url.attribute, url.path)).collect();
+        assert_eq!(urls, vec![("href", "../guide".to_string()), ("src", "img/my pic.png".to_string())]);
+        assert_eq!(local_urls(html)[1].raw, "img/my%20pic.png?v=1");
+        assert_eq!(local_urls(html)[1].tag, "img");
+    }
+
+    #[test]
+    fn relative_urls_resolve_against_the_route() {
+        assert_eq!(join_route("/docs/guide/usage", "../basics/setup"), "/docs/basics/setup");
+        assert_eq!(join_route("/docs/usage", "./setup"), "/docs/setup");
+        assert_eq!(join_route("/usage", "../../x"), "/x");
+    }
+
+    #[test]
+    fn urls_are_checked_against_routes_and_files() {
+        let src = manifest_dir().join("../tests/assets/content/index.md");
+        let routes = HashSet::from(["/docs/setup".to_string(), "/".to_string()]);
+
+        assert!(check_url(&src, Some("/docs/usage"), "image.svg", &routes));
+        assert!(check_url(&src, None, "../content/image.svg", &routes));
+        assert!(!check_url(&src, None, "../tests/assets/content/image.svg", &routes));
+        assert!(check_url(&src, None, "/docs/setup/", &routes));
+        assert!(check_url(&src, Some("/docs/usage"), "setup", &routes));
+        assert!(!check_url(&src, Some("/docs/usage"), "missing.svg", &routes));
+        assert!(!check_url(&src, None, "/docs/missing", &routes));
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use pandoc::{InputFormat, MarkdownExtension, OutputFormat, PandocOption};
+use proc_macro2::{Span, TokenStream};
+use syn::parse::Parser;
+use toml_edit::{ImDocument, Item, Value};
+
+use crate::{
+    arg::parse_pandoc_options,
+    error::Errors,
+    options::{Element, parse_input_format, parse_output_formats},
+    paths,
+    sidecar::rebase,
+};
+
+/// Keys a configuration file may set
+const KEYS: &[&str] = &["content", "source_ext", "input_format", "output_format", "options", "nproc"];
+
+/// Arguments read from a `config = "..."` file, `None` for every key the
+/// file leaves out
+#[derive(Default)]
+pub(crate) struct ConfigFile {
+    pub content: Option<Vec<Element>>,
+    pub source_ext: Option<String>,
+    pub input_format: Option<(InputFormat, Vec<MarkdownExtension>)>,
+    pub output_format: Option<Vec<(OutputFormat, Vec<MarkdownExtension>)>>,
+    pub pandoc_options: Option<Vec<PandocOption>>,
+    pub nproc: Option<usize>,
+}
+
+/// Where errors in the file are reported: its path for the message and the
+/// `config` argument for the span
+pub(crate) struct Source<'a> {
+    pub path: &'a str,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Source<'a> {
+    pub(crate) fn error(&self, range: Option<Range<usize>>, key: &str, message: impl std::fmt::Display) -> syn::Error {
+        let line = range.map_or(1, |range| self.text[..range.start].matches('\n').count() + 1);
+        syn::Error::new(self.span, format!("{}:{}: `{}`: {}", self.path, line, key, message))
+    }
+
+    pub(crate) fn document(&self) -> syn::Result<ImDocument<&'a str>> {
+        ImDocument::parse(self.text).map_err(|e| {
+            let line = e.span().map_or(1, |range| self.text[..range.start].matches('\n').count() + 1);
+            syn::Error::new(self.span, format!("{}:{}: {}", self.path, line, e.message()))
+        })
+    }
+}
+
+impl ConfigFile {
+    /// Read `path`, errors pointing at `span`. Relative paths in the file
+    /// resolve against its directory, so crates can share it.
+    pub(crate) fn load(path: &PathBuf, span: Span) -> syn::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| syn::Error::new(span, format!("Failed to read {}: {}", path.display(), e)))?;
+        let display = path.display().to_string();
+        Self::parse(&text, &display, path.parent().unwrap_or(Path::new("")), span)
+    }
+
+    fn parse(text: &str, path: &str, dir: &Path, span: Span) -> syn::Result<Self> {
+        let source = Source { path, text, span };
+        let document = source.document()?;
+
+        let mut config = ConfigFile::default();
+        let mut errors = Errors::default();
+        for (key, item) in document.iter() {
+            let range = document.key(key).and_then(|key| key.span()).or_else(|| item.span());
+            let result = match key {
+                "content" => parse_content(item, &source, dir).map(|v| config.content = Some(v)),
+                "source_ext" => string(item, &source, key).map(|v| config.source_ext = Some(v)),
+                "input_format" => macro_syntax(item, &source, key, parse_input_format)
+                    .map(|v| config.input_format = Some(v)),
+                "output_format" => output_formats(item, &source).map(|v| config.output_format = Some(v)),
+                "options" => pandoc_options(item, &source, dir).map(|v| config.pandoc_options = Some(v)),
+                "nproc" => match item.as_integer() {
+                    Some(n) if n > 0 => {
+                        config.nproc = Some(n as usize);
+                        Ok(())
+                    }
+                    _ => Err(source.error(item.span(), key, "expected a positive integer")),
+                },
+                _ => Err(source.error(range, key, format!("unknown key, expected one of {}", KEYS.join(", ")))),
+            };
+
+            if let Err(e) = result {
+                errors.push(e);
+            }
+        }
+
+        errors.finish()?;
+        Ok(config)
+    }
+}
+
+fn string(item: &Item, source: &Source, key: &str) -> syn::Result<String> {
+    item.as_str()
+        .map(str::to_string)
+        .ok_or_else(|| source.error(item.span(), key, "expected a string"))
+}
+
+/// A format in the macro's syntax, `"Markdown[Smart]"`, or as a table,
+/// `{ format = "Markdown", extensions = ["Smart"] }`
+pub(crate) fn format_syntax(value: &Value, source: &Source, key: &str) -> syn::Result<String> {
+    if let Some(format) = value.as_str() {
+        return Ok(format.to_string());
+    }
+
+    let invalid = || source.error(value.span(), key, "expected a format name or { format, extensions }");
+    let table = value.as_inline_table().ok_or_else(invalid)?;
+    let format = table.get("format").and_then(Value::as_str).ok_or_else(invalid)?;
+    let extensions = match table.get("extensions") {
+        Some(list) => {
+            let names: Option<Vec<&str>> = list.as_array().and_then(|list| list.iter().map(Value::as_str).collect());
+            names.ok_or_else(|| source.error(list.span(), key, "extensions should be a list of names"))?
+        }
+        None => Vec::new(),
+    };
+    if let Some((other, _)) = table.iter().find(|(k, _)| !matches!(*k, "format" | "extensions")) {
+        return Err(source.error(value.span(), key, format!("unknown field `{}`, expected format or extensions", other)));
+    }
+
+    Ok(format!("{}[{}]", format, extensions.join(", ")))
+}
+
+/// Run one of the macro's parsers on a format written in the file
+fn macro_syntax<T>(
+    item: &Item,
+    source: &Source,
+    key: &str,
+    parser: fn(syn::parse::ParseStream) -> syn::Result<T>,
+) -> syn::Result<T> {
+    let value = item
+        .as_value()
+        .ok_or_else(|| source.error(item.span(), key, "expected a string or an inline table"))?;
+    let text = format_syntax(value, source, key)?;
+    parser.parse_str(&text).map_err(|e| source.error(value.span(), key, e))
+}
+
+/// One format or a list of them, parsed as the macro's bracketed list
+fn output_formats(item: &Item, source: &Source) -> syn::Result<Vec<(OutputFormat, Vec<MarkdownExtension>)>> {
+    let key = "output_format";
+    let Some(list) = item.as_array() else {
+        return macro_syntax(item, source, key, parse_output_formats);
+    };
+
+    let formats = list
+        .iter()
+        .map(|value| format_syntax(value, source, key))
+        .collect::<syn::Result<Vec<String>>>()?;
+    parse_output_formats
+        .parse_str(&format!("[{}]", formats.join(", ")))
+        .map_err(|e| source.error(item.span(), key, e))
+}
+
+/// Pandoc options, each in the macro's syntax: `'DataDir { "data" }'`, with
+/// relative files rebased onto `dir`
+fn pandoc_options(item: &Item, source: &Source, dir: &Path) -> syn::Result<Vec<PandocOption>> {
+    let key = "options";
+    let list = item
+        .as_array()
+        .ok_or_else(|| source.error(item.span(), key, "expected a list of options"))?;
+
+    let mut options = Vec::new();
+    for value in list.iter() {
+        let option = value
+            .as_str()
+            .ok_or_else(|| source.error(value.span(), key, "expected an option such as \"Standalone\""))?;
+        options.extend(parse_option(option).map_err(|e| source.error(value.span(), key, e))?);
+    }
+    for option in &mut options {
+        rebase(option, dir);
+    }
+
+    Ok(options)
+}
+
+/// One option in the macro's syntax, which may expand to several
+pub(crate) fn parse_option(option: &str) -> Result<Vec<PandocOption>, String> {
+    let tokens = TokenStream::from_str(option).map_err(|e| e.to_string())?;
+    parse_pandoc_options(tokens).map_err(|e| e.to_string())
+}
+
+/// Fields of one `content` entry, with the entry's position in the file
+type Entry<'a> = (Option<Range<usize>>, Vec<(&'a str, &'a Value)>);
+
+/// `[[content]]` tables or an inline list, each either
+/// `{ path = "...", route = "..." }` or `{ special = "..." }`, paths being
+/// resolved against `dir`
+fn parse_content(item: &Item, source: &Source, dir: &Path) -> syn::Result<Vec<Element>> {
+    let key = "content";
+    let tables: Vec<Entry> = match item {
+        Item::ArrayOfTables(tables) => tables
+            .iter()
+            .map(|table| {
+                let fields = table.iter().filter_map(|(k, v)| Some((k, v.as_value()?))).collect();
+                (table.span(), fields)
+            })
+            .collect(),
+        Item::Value(Value::Array(list)) => list
+            .iter()
+            .map(|value| match value.as_inline_table() {
+                Some(table) => Ok((value.span(), table.iter().collect())),
+                None => Err(source.error(value.span(), key, "expected a { path, route } table")),
+            })
+            .collect::<syn::Result<_>>()?,
+        _ => return Err(source.error(item.span(), key, "expected [[content]] tables or a list of them")),
+    };
+
+    let mut elements = Vec::new();
+    for (range, fields) in tables {
+        let field = |name: &str| -> syn::Result<Option<String>> {
+            match fields.iter().find(|(k, _)| *k == name) {
+                Some((_, value)) => match value.as_str() {
+                    Some(s) => Ok(Some(s.to_string())),
+                    None => Err(source.error(value.span(), key, format!("{} should be a string", name))),
+                },
+                None => Ok(None),
+            }
+        };
+        if let Some((other, _)) = fields.iter().find(|(k, _)| !matches!(*k, "path" | "route" | "special")) {
+            return Err(source.error(range, key, format!("unknown field `{}`, expected path, route or special", other)));
+        }
+
+        elements.push(match (field("path")?, field("special")?) {
+            (Some(path), None) => Element::CompileFromPath {
+                path: paths::resolve(&path, None, dir)
+                    .map_err(|e| source.error(range.clone(), key, e))?
+                    .display()
+                    .to_string(),
+                route: field("route")?,
+                span: source.span,
+            },
+            (None, Some(ty)) => Element::Special { ty },
+            _ => return Err(source.error(range, key, "every entry needs either path or special")),
+        });
+    }
+
+    if elements.is_empty() {
+        return Err(source.error(item.span(), key, "no content sources"));
+    }
+    Ok(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> syn::Result<ConfigFile> {
+        ConfigFile::parse(text, "site.toml", Path::new("/work/site"), Span::call_site())
+    }
+
+    #[test]
+    fn every_key_is_read() {
+        let config = parse(
+            r#"
+            nproc = 8
+            source_ext = "md"
+            input_format = { format = "Markdown", extensions = ["Smart", "Footnotes"] }
+            output_format = ["Html5", "Plain"]
+            options = ["Standalone", 'DataDir { "data" }']
+
+            [[content]]
+            path = "docs"
+            route = "/docs"
+
+            [[content]]
+            special = "separator"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.nproc, Some(8));
+        assert_eq!(config.source_ext.as_deref(), Some("md"));
+        let (input, extensions) = config.input_format.unwrap();
+        assert_eq!(input.to_string(), "markdown");
+        assert_eq!(extensions.len(), 2);
+        let outputs: Vec<String> = config.output_format.unwrap().iter().map(|(f, _)| f.to_string()).collect();
+        assert_eq!(outputs, ["html5", "plain"]);
+        assert!(matches!(
+            config.pandoc_options.as_deref(),
+            Some([PandocOption::Standalone, PandocOption::DataDir(dir)]) if dir == Path::new("/work/site/data")
+        ));
+
+        let content = config.content.unwrap();
+        assert!(matches!(
+            &content[0],
+            Element::CompileFromPath { path, route: Some(route), .. } if path == "/work/site/docs" && route == "/docs"
+        ));
+        assert!(matches!(&content[1], Element::Special { ty } if ty == "separator"));
+    }
+
+    #[test]
+    fn formats_accept_the_macro_syntax() {
+        let config = parse("input_format = \"Markdown[Smart]\"\noutput_format = \"Html\"\ncontent = [{ path = \"a\" }]").unwrap();
+        assert_eq!(config.input_format.unwrap().1.len(), 1);
+        assert_eq!(config.output_format.unwrap().len(), 1);
+        assert!(matches!(&config.content.unwrap()[0], Element::CompileFromPath { route: None, .. }));
+    }
+
+    #[test]
+    fn paths_resolve_against_the_file() {
+        let config = parse(
+            "content = [{ path = \"../shared\" }, { path = \"/srv/docs\" }]\noptions = ['Template { \"/srv/page.html\" }', 'IncludeInHeader { \"head.html\" }']",
+        )
+        .unwrap();
+
+        let paths: Vec<&str> = config
+            .content
+            .iter()
+            .flatten()
+            .filter_map(|element| match element {
+                Element::CompileFromPath { path, .. } => Some(path.as_str()),
+                Element::Special { .. } => None,
+            })
+            .collect();
+        assert_eq!(paths, ["/work/site/../shared", "/srv/docs"]);
+        let options: Vec<String> = config.pandoc_options.unwrap().iter().map(|o| format!("{:?}", o)).collect();
+        assert_eq!(options, ["Template(\"/srv/page.html\")", "IncludeInHeader(\"/work/site/head.html\")"]);
+    }
+
+    #[test]
+    fn errors_report_file_line_and_key() {
+        let err = parse("nproc = 4\n\noutput_format = 3\nthreads = 2\n").err().unwrap();
+        let messages: Vec<String> = err.into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "site.toml:3: `output_format`: expected a format name or { format, extensions }",
+                "site.toml:4: `threads`: unknown key, expected one of content, source_ext, input_format, output_format, options, nproc",
+            ]
+        );
+
+        let err = parse("options = [\"Standalone\",\n  \"NoSuchOption\"]").err().unwrap();
+        assert!(err.to_string().starts_with("site.toml:2: `options`: "), "{}", err);
+
+        let err = parse("nproc = 2\n\n[[content]]\nroute = \"/\"").err().unwrap();
+        assert_eq!(err.to_string(), "site.toml:3: `content`: every entry needs either path or special");
+
+        let err = parse("nproc = ").err().unwrap();
+        assert!(err.to_string().starts_with("site.toml:1: "), "{}", err);
+    }
+}
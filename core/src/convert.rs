@@ -0,0 +1,262 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use pandoc::{InputFormat, MarkdownExtension, OutputFormat, Pandoc, PandocError, PandocOption, PandocOutput};
+
+use crate::{
+    arg::reader_options,
+    backend::Backend,
+    mock,
+    options::Options,
+    sidecar::SourceOptions,
+};
+
+/// Everything a worker thread needs to convert one source file to one format
+#[derive(Clone)]
+pub(crate) struct ConversionSettings {
+    pub input_format: Option<(InputFormat, Vec<MarkdownExtension>)>,
+    pub output_format: Option<(OutputFormat, Vec<MarkdownExtension>)>,
+    pub pandoc_options: Vec<PandocOption>,
+    pub backend: Backend,
+    /// Searched for `pandoc` before PATH, set once the backend is probed
+    pub pandoc_dir: Option<PathBuf>,
+}
+
+impl ConversionSettings {
+    /// One set of settings per requested output format, primary format first
+    pub(crate) fn for_each_format(options: &Options) -> Vec<Self> {
+        let formats = if options.output_format.is_empty() {
+            vec![None]
+        } else {
+            options.output_format.iter().cloned().map(Some).collect()
+        };
+
+        formats
+            .into_iter()
+            .map(|output_format| Self {
+                input_format: options.input_format.clone(),
+                output_format,
+                pandoc_options: options.pandoc_options.clone(),
+                backend: options.backend,
+                pandoc_dir: None,
+            })
+            .collect()
+    }
+
+    /// These settings with the input format and options of one source
+    pub(crate) fn for_source(&self, options: &SourceOptions) -> Self {
+        Self {
+            input_format: options.input_format.clone(),
+            pandoc_options: options.pandoc_options.clone(),
+            ..self.clone()
+        }
+    }
+
+    fn pandoc(&self) -> Pandoc {
+        let mut pandoc = Pandoc::new();
+        if let Some(dir) = &self.pandoc_dir {
+            pandoc.add_pandoc_path_hint(dir);
+        }
+        pandoc
+    }
+
+    /// Name the rendering is looked up by, e.g. `html5` or `plain`
+    pub(crate) fn format_name(&self) -> String {
+        self.output_format
+            .as_ref()
+            .map_or("html".to_string(), |(fmt, _)| fmt.to_string())
+    }
+}
+
+/// Render one source file to the settings' output format
+pub(crate) fn render(src: PathBuf, settings: &ConversionSettings) -> Result<PandocOutput, String> {
+    match settings.backend {
+        Backend::Pandoc => {}
+        Backend::Native => return render_native(src, settings),
+        Backend::Mock => return mock::render(&src, settings),
+    }
+
+    let mut pandoc = settings.pandoc();
+    pandoc.add_options(&settings.pandoc_options);
+
+    if let Some((fmt, exts)) = settings.input_format.clone() {
+        pandoc.set_input_format(fmt, exts);
+    }
+
+    if let Some((fmt, exts)) = settings.output_format.clone() {
+        pandoc.set_output_format(fmt, exts);
+    }
+
+    pandoc.set_input(pandoc::InputKind::Files(vec![src]));
+    match settings.output_format.as_ref().map(|(fmt, _)| fmt) {
+        Some(fmt) if is_binary(fmt) => Ok(PandocOutput::ToBufferRaw(execute_to_file(pandoc, fmt)?)),
+        _ => {
+            pandoc.set_output(pandoc::OutputKind::Pipe);
+            pandoc.execute().map_err(|e| describe_error(&e))
+        }
+    }
+}
+
+/// Read one source file into pandoc's JSON AST, used for the metadata block
+pub(crate) fn read_ast(src: PathBuf, settings: &ConversionSettings) -> Result<String, String> {
+    match settings.backend {
+        Backend::Pandoc => {}
+        Backend::Native => return read_ast_native(src, settings),
+        Backend::Mock => return mock::read_ast(&src, settings),
+    }
+
+    let mut pandoc = settings.pandoc();
+    pandoc.add_options(&reader_options(&settings.pandoc_options));
+
+    if let Some((fmt, exts)) = settings.input_format.clone() {
+        pandoc.set_input_format(fmt, exts);
+    }
+
+    pandoc.set_output_format(pandoc::OutputFormat::Json, Vec::new());
+    pandoc.set_input(pandoc::InputKind::Files(vec![src]));
+    pandoc.set_output(pandoc::OutputKind::Pipe);
+    match pandoc.execute().map_err(|e| describe_error(&e))? {
+        PandocOutput::ToBuffer(json) => Ok(json),
+        _ => Err("pandoc did not write the JSON AST to the pipe".to_string()),
+    }
+}
+
+#[cfg(feature = "native")]
+fn render_native(src: PathBuf, settings: &ConversionSettings) -> Result<PandocOutput, String> {
+    crate::native::render(&src, settings)
+}
+
+#[cfg(feature = "native")]
+fn read_ast_native(src: PathBuf, settings: &ConversionSettings) -> Result<String, String> {
+    crate::native::read_ast(&src, settings)
+}
+
+// Unreachable, `backend = Native` does not parse without the feature
+#[cfg(not(feature = "native"))]
+fn render_native(_: PathBuf, _: &ConversionSettings) -> Result<PandocOutput, String> {
+    Err("the native backend is not enabled".to_string())
+}
+
+#[cfg(not(feature = "native"))]
+fn read_ast_native(_: PathBuf, _: &ConversionSettings) -> Result<String, String> {
+    Err("the native backend is not enabled".to_string())
+}
+
+/// Binary formats pandoc refuses to (or cannot faithfully) write to a pipe
+pub(crate) fn is_binary(fmt: &OutputFormat) -> bool {
+    match fmt {
+        OutputFormat::Pdf | OutputFormat::Docx | OutputFormat::Odt | OutputFormat::Epub | OutputFormat::Epub3 => true,
+        OutputFormat::Other(name) => matches!(name.as_str(), "pptx" | "epub2"),
+        _ => false,
+    }
+}
+
+/// MIME type of a binary output format
+pub(crate) fn mime_type(fmt: &OutputFormat) -> &'static str {
+    match fmt {
+        OutputFormat::Pdf => "application/pdf",
+        OutputFormat::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        OutputFormat::Odt => "application/vnd.oasis.opendocument.text",
+        OutputFormat::Epub | OutputFormat::Epub3 => "application/epub+zip",
+        OutputFormat::Other(name) if name == "epub2" => "application/epub+zip",
+        OutputFormat::Other(name) if name == "pptx" => {
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+        }
+        _ => "application/octet-stream",
+    }
+}
+
+/// Let pandoc write to a temporary file and read the result back
+fn execute_to_file(mut pandoc: Pandoc, fmt: &OutputFormat) -> Result<Vec<u8>, String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let tmp = env::temp_dir().join(format!(
+        "pandoc-generator-{}-{}.{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        fmt
+    ));
+
+    pandoc.set_output(pandoc::OutputKind::File(tmp.clone()));
+    let result = pandoc
+        .execute()
+        .map_err(|e| describe_error(&e))
+        .and_then(|_| fs::read(&tmp).map_err(|e| format!("failed to read pandoc output {}: {}", tmp.display(), e)));
+
+    let _ = fs::remove_file(&tmp);
+    result
+}
+
+/// Human readable description of a pandoc failure, preferring its stderr
+pub(crate) fn describe_error(err: &PandocError) -> String {
+    match err {
+        PandocError::Err(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stderr = stderr.trim();
+            match output.status.code() {
+                Some(code) if stderr.is_empty() => format!("pandoc exited with status {}", code),
+                Some(code) => format!("pandoc exited with status {}:\n{}", code, stderr),
+                None if stderr.is_empty() => "pandoc was terminated by a signal".to_string(),
+                None => format!("pandoc was terminated by a signal:\n{}", stderr),
+            }
+        }
+        PandocError::PandocNotFound => "pandoc not found, is it installed and on PATH?".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn failed_output(code: i32, stderr: &str) -> PandocError {
+        use std::os::unix::process::ExitStatusExt;
+
+        PandocError::Err(std::process::Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn error_includes_stderr() {
+        let err = failed_output(64, "Unknown extension: foo\n");
+        assert_eq!(
+            describe_error(&err),
+            "pandoc exited with status 64:\nUnknown extension: foo"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn error_without_stderr_reports_status() {
+        let err = failed_output(1, "  ");
+        assert_eq!(describe_error(&err), "pandoc exited with status 1");
+    }
+
+    #[test]
+    fn binary_formats_are_detected() {
+        assert!(is_binary(&OutputFormat::Pdf));
+        assert!(is_binary(&OutputFormat::Epub3));
+        assert!(is_binary(&OutputFormat::Other("pptx".to_string())));
+        assert!(!is_binary(&OutputFormat::Html5));
+        assert!(!is_binary(&OutputFormat::Latex));
+        assert_eq!(mime_type(&OutputFormat::Epub), "application/epub+zip");
+        assert_eq!(mime_type(&OutputFormat::Pdf), "application/pdf");
+    }
+
+    #[test]
+    fn missing_pandoc_is_explained() {
+        assert_eq!(
+            describe_error(&PandocError::PandocNotFound),
+            "pandoc not found, is it installed and on PATH?"
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use pandoc::{InputFormat, MarkdownExtension, OutputFormat};
+use proc_macro2::Span;
+
+use crate::{options::Options, paths};
+
+/// Overrides `pandoc_path`
+const PANDOC_ENV: &str = "PANDOC_GENERATOR_PANDOC";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// Requirement on the pandoc version, e.g. `>=3.1` or `>=3.1, <4`. A bare
+/// version matches every release it is a prefix of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VersionReq(Vec<(Op, Vec<u32>)>);
+
+fn parse_version(text: &str) -> Option<Vec<u32>> {
+    text.split('.').map(|part| part.parse().ok()).collect()
+}
+
+impl VersionReq {
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+
+        for part in text.split(',') {
+            let part = part.trim();
+            let (op, version) = [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt), ("=", Op::Eq)]
+                .into_iter()
+                .find_map(|(prefix, op)| part.strip_prefix(prefix).map(|rest| (op, rest.trim())))
+                .unwrap_or((Op::Eq, part));
+
+            match parse_version(version) {
+                Some(version) => parts.push((op, version)),
+                None => return Err(format!("invalid pandoc version requirement {:?}, expected e.g. \">=3.1\"", text)),
+            }
+        }
+
+        Ok(Self(parts))
+    }
+
+    pub(crate) fn matches(&self, version: &[u32]) -> bool {
+        self.0.iter().all(|(op, wanted)| {
+            // Missing components count as zero
+            let len = wanted.len().max(version.len());
+            let pad = |v: &[u32]| (0..len).map(|i| v.get(i).copied().unwrap_or(0)).collect::<Vec<_>>();
+            let ordering = pad(version).cmp(&pad(wanted));
+
+            match op {
+                Op::Eq => version.starts_with(wanted),
+                Op::Gt => ordering.is_gt(),
+                Op::Ge => ordering.is_ge(),
+                Op::Lt => ordering.is_lt(),
+                Op::Le => ordering.is_le(),
+            }
+        })
+    }
+}
+
+/// The pandoc the conversions run
+pub(crate) struct PandocBinary {
+    /// Directory holding the `pandoc` executable, searched before PATH
+    pub dir: Option<PathBuf>,
+    /// First line of `pandoc --version`, e.g. `pandoc 3.1.11`
+    pub version: String,
+}
+
+/// Directory of the configured pandoc: `$PANDOC_GENERATOR_PANDOC`, then
+/// `pandoc_path`, either naming the executable or its directory. `None`
+/// searches PATH.
+fn pandoc_dir(
+    configured: Option<&str>,
+    env: Option<OsString>,
+    base: Option<&str>,
+    root: &Path,
+) -> Result<Option<PathBuf>, String> {
+    let path = match (env, configured) {
+        (Some(path), _) => paths::resolve(&path.to_string_lossy(), None, root)?,
+        (None, Some(path)) => paths::resolve(path, base, root)?,
+        (None, None) => return Ok(None),
+    };
+
+    if path.is_dir() {
+        return Ok(Some(path));
+    }
+
+    // The pandoc crate can only run an executable called `pandoc`
+    match path.file_stem() {
+        Some(stem) if stem == "pandoc" => Ok(path.parent().map(Path::to_path_buf)),
+        _ => Err(format!(
+            "pandoc_path must point at an executable named `pandoc` or its directory, got {}",
+            path.display()
+        )),
+    }
+}
+
+/// Version numbers in the first line of `pandoc --version`
+fn version_numbers(line: &str) -> Option<Vec<u32>> {
+    line.split_whitespace().nth(1).and_then(parse_version)
+}
+
+fn run(dir: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let program = dir.map_or(PathBuf::from("pandoc"), |dir| dir.join("pandoc"));
+    let output = Command::new(&program).args(args).output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!(
+            "pandoc not found ({}), install it, point pandoc_path or ${} at it, or use backend = Native",
+            match dir {
+                Some(_) => format!("looked for {}", program.display()),
+                None => "looked on PATH".to_string(),
+            },
+            PANDOC_ENV
+        ),
+        _ => format!("failed to run {}: {}", program.display(), e),
+    })?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "`{} {}` failed: {}",
+            program.display(),
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Extensions pandoc supports for `format`, as listed by
+/// `pandoc --list-extensions=FORMAT`
+fn supported_extensions(listing: &str) -> Vec<&str> {
+    listing.lines().map(|l| l.trim().trim_start_matches(['+', '-'])).filter(|l| !l.is_empty()).collect()
+}
+
+/// Requested formats or extensions pandoc does not know about
+pub(crate) fn check_capabilities(
+    dir: Option<&Path>,
+    version: &str,
+    input: Option<&(InputFormat, Vec<MarkdownExtension>)>,
+    outputs: &[(OutputFormat, Vec<MarkdownExtension>)],
+) -> Result<(), String> {
+    let input_formats = run(dir, &["--list-input-formats"])?;
+    let output_formats = run(dir, &["--list-output-formats"])?;
+    let mut problems = Vec::new();
+
+    let mut requested = Vec::new();
+    if let Some((fmt, exts)) = input {
+        requested.push((fmt.to_string(), exts, input_formats.lines().any(|l| l.trim() == fmt.to_string())));
+    }
+    for (fmt, exts) in outputs {
+        // PDF goes through a LaTeX engine and is not listed
+        let known = matches!(fmt, OutputFormat::Pdf) || output_formats.lines().any(|l| l.trim() == fmt.to_string());
+        requested.push((fmt.to_string(), exts, known));
+    }
+
+    for (fmt, exts, known) in requested {
+        if !known {
+            problems.push(format!("format `{}` is not supported by {}", fmt, version));
+            continue;
+        }
+        if exts.is_empty() {
+            continue;
+        }
+
+        let listing = run(dir, &[&format!("--list-extensions={}", fmt)])?;
+        let supported = supported_extensions(&listing);
+        for ext in exts {
+            if !supported.contains(&ext.to_string().as_str()) {
+                problems.push(format!("extension `{}` is not supported by {} for `{}`", ext, version, fmt));
+            }
+        }
+    }
+
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems.join("\n")),
+    }
+}
+
+/// Find pandoc, check its version against `pandoc_version` and that it
+/// supports every requested format and extension
+pub(crate) fn probe(options: &Options) -> syn::Result<PandocBinary> {
+    let call_site = |e: String| syn::Error::new(Span::call_site(), e);
+
+    let env_path = env::var_os(PANDOC_ENV);
+    // Point at `pandoc_path` when it picked the executable
+    let path_span = match (&env_path, &options.pandoc_path) {
+        (None, Some((_, span))) => *span,
+        _ => Span::call_site(),
+    };
+    let configured = options.pandoc_path.as_ref().map(|(path, _)| path.as_str());
+    let dir = pandoc_dir(configured, env_path, options.base.as_deref(), &options.root)
+        .map_err(|e| syn::Error::new(path_span, e))?;
+
+    let output = run(dir.as_deref(), &["--version"]).map_err(|e| syn::Error::new(path_span, e))?;
+    let version = output.lines().next().unwrap_or_default().trim().to_string();
+
+    if let Some((req, span)) = &options.pandoc_version {
+        match version_numbers(&version) {
+            Some(numbers) if req.matches(&numbers) => {}
+            Some(_) => {
+                return Err(syn::Error::new(
+                    *span,
+                    format!("found {}, which does not satisfy pandoc_version", version),
+                ));
+            }
+            None => return Err(syn::Error::new(*span, format!("cannot tell the version of {:?}", version))),
+        }
+    }
+
+    check_capabilities(dir.as_deref(), &version, options.input_format.as_ref(), &options.output_format)
+        .map_err(call_site)?;
+
+    Ok(PandocBinary { dir, version })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements_are_parsed() {
+        assert_eq!(VersionReq::parse(">=3.1").unwrap(), VersionReq(vec![(Op::Ge, vec![3, 1])]));
+        assert_eq!(
+            VersionReq::parse(">= 3.1, <4").unwrap(),
+            VersionReq(vec![(Op::Ge, vec![3, 1]), (Op::Lt, vec![4])])
+        );
+        assert!(VersionReq::parse(">=3.x").is_err());
+        assert!(VersionReq::parse("").is_err());
+    }
+
+    #[test]
+    fn requirements_are_matched() {
+        let req = VersionReq::parse(">=3.1, <4").unwrap();
+        assert!(req.matches(&[3, 1]));
+        assert!(req.matches(&[3, 1, 11]));
+        assert!(!req.matches(&[3, 0, 1]));
+        assert!(!req.matches(&[4, 0]));
+
+        let exact = VersionReq::parse("3.1").unwrap();
+        assert!(exact.matches(&[3, 1, 11]));
+        assert!(!exact.matches(&[3, 10]));
+        assert!(VersionReq::parse(">3.1").unwrap().matches(&[3, 1, 1]));
+    }
+
+    #[test]
+    fn versions_are_read_from_the_first_line() {
+        assert_eq!(version_numbers("pandoc 3.1.11"), Some(vec![3, 1, 11]));
+        assert_eq!(version_numbers("pandoc.exe 2.19.2"), Some(vec![2, 19, 2]));
+        assert_eq!(version_numbers("pandoc"), None);
+    }
+
+    #[test]
+    fn environment_overrides_pandoc_path() {
+        let dir = env::temp_dir();
+        let tool = dir.join("pandoc");
+        let env_tool = OsString::from(dir.join("bin/pandoc"));
+        let root = &dir;
+
+        assert_eq!(pandoc_dir(None, None, None, root), Ok(None));
+        assert_eq!(pandoc_dir(tool.to_str(), None, None, root), Ok(Some(dir.clone())));
+        assert_eq!(pandoc_dir(dir.to_str(), None, None, root), Ok(Some(dir.clone())));
+        assert_eq!(pandoc_dir(Some("nope"), Some(env_tool), None, root), Ok(Some(dir.join("bin"))));
+        assert!(pandoc_dir(Some("/usr/bin/markdown"), None, None, root).unwrap_err().contains("named `pandoc`"));
+    }
+
+    #[test]
+    fn extension_listings_are_parsed() {
+        assert_eq!(supported_extensions("+smart\n-ascii_identifiers\n\n"), vec!["smart", "ascii_identifiers"]);
+    }
+}
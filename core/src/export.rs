@@ -0,0 +1,137 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde_json::Value;
+
+/// Name of the manifest `Export::write_to` writes next to the pages
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A content tree rendered to files laid out by route, with a JSON
+/// manifest describing the tree
+pub struct Export {
+    /// Contents keyed by `/`-separated path relative to the output directory
+    pub(crate) files: BTreeMap<String, Vec<u8>>,
+    pub(crate) manifest: Value,
+    /// Broken links reported when `check_links` is `warn`
+    pub(crate) warnings: Vec<String>,
+}
+
+impl Export {
+    /// Every file to write, sorted by path
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.files.iter().map(|(path, contents)| (path.as_str(), contents.as_slice()))
+    }
+
+    /// The tree as the generated `ContentTree`, with the file of every
+    /// rendering in place of its contents
+    pub fn manifest(&self) -> &Value {
+        &self.manifest
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Write every file and the manifest under `dir`, replacing existing
+    /// files but leaving others in place
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        for (path, contents) in self.files() {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+
+        fs::create_dir_all(dir)?;
+        let manifest = serde_json::to_string_pretty(&self.manifest).map_err(io::Error::other)?;
+        fs::write(dir.join(MANIFEST_FILE), manifest + "\n")
+    }
+}
+
+/// Directory holding the files of the page at `route`, relative to the
+/// output directory and empty for `/`
+pub(crate) fn route_dir(route: &str) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for part in route.split('/').filter(|part| !part.is_empty()) {
+        if part == "." || part == ".." || part.contains('\\') {
+            return Err(format!("route `{}` cannot be written to a directory", route));
+        }
+        parts.push(part);
+    }
+
+    Ok(parts.join("/"))
+}
+
+/// File of one rendering of a page: `index.<ext>` in its route directory,
+/// or `index.<format>.<ext>` when an earlier format took that name
+pub(crate) fn page_file(dir: &str, format: &str, taken: impl Fn(&str) -> bool) -> String {
+    let join = |name: String| if dir.is_empty() { name } else { format!("{}/{}", dir, name) };
+
+    let ext = extension(format);
+    let file = join(format!("index.{}", ext));
+    if !taken(&file) {
+        return file;
+    }
+    join(format!("index.{}.{}", format, ext))
+}
+
+/// Usual file extension of pandoc's output format `format`
+fn extension(format: &str) -> &str {
+    match format {
+        "html" | "html4" | "html5" | "revealjs" | "slidy" | "slideous" | "dzslides" | "s5" => "html",
+        "plain" => "txt",
+        "markdown" | "markdown_strict" | "markdown_phpextra" | "markdown_mmd" | "markdown_github" | "gfm"
+        | "commonmark" | "commonmark_x" => "md",
+        "latex" | "beamer" | "context" => "tex",
+        "docbook" | "docbook4" | "docbook5" | "jats" | "tei" => "xml",
+        "asciidoc" => "adoc",
+        "mediawiki" | "dokuwiki" => "wiki",
+        "texinfo" => "texi",
+        "epub2" | "epub3" => "epub",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_become_directories() {
+        assert_eq!(route_dir("/").unwrap(), "");
+        assert_eq!(route_dir("/docs/intro/").unwrap(), "docs/intro");
+        assert_eq!(route_dir("docs//intro").unwrap(), "docs/intro");
+        assert!(route_dir("/docs/../secrets").is_err());
+    }
+
+    #[test]
+    fn renderings_are_named_by_format() {
+        let nothing = |_: &str| false;
+        assert_eq!(page_file("", "html5", nothing), "index.html");
+        assert_eq!(page_file("docs/intro", "plain", nothing), "docs/intro/index.txt");
+        assert_eq!(page_file("docs", "docx", nothing), "docs/index.docx");
+
+        let html_taken = |file: &str| file == "docs/index.html";
+        assert_eq!(page_file("docs", "revealjs", html_taken), "docs/index.revealjs.html");
+    }
+
+    #[test]
+    fn files_and_manifest_are_written() {
+        let dir = std::env::temp_dir().join(format!("pandoc-generator-export-{}", std::process::id()));
+        let export = Export {
+            files: BTreeMap::from([
+                ("index.html".to_string(), b"<p>home</p>".to_vec()),
+                ("docs/intro/index.html".to_string(), b"<p>intro</p>".to_vec()),
+            ]),
+            manifest: serde_json::json!({ "tree": { "kind": "nested" } }),
+            warnings: Vec::new(),
+        };
+
+        export.write_to(&dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("docs/intro/index.html")).unwrap(), "<p>intro</p>");
+        let manifest: Value = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest, export.manifest);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1534 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Element, FsTree, Options,
+    ast::{Document, Heading, MetaValue},
+    assets::{Assets, data_uri, resolve_file},
+    check::{LinkCheck, check_url, local_urls, warning},
+    convert::{ConversionSettings, mime_type},
+    error::Errors,
+    export::{Export, page_file, route_dir},
+    links::{LinkResolver, LinkTarget, rewrite_urls},
+    schema::{MetadataSchema, parse_date},
+    search::{SearchDocument, SearchIndex},
+    sidecar::SourceOptions,
+    site::{SitePage, atom_feed, sitemap_xml, summarize},
+    tree::TreeElement,
+};
+use pandoc::PandocOutput;
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, quote};
+use serde_json::{Map, Value, json};
+
+fn generate_option<T: ToTokens>(arg: &Option<T>) -> TokenStream {
+    match arg {
+        Some(v) => quote! { Some(#v) },
+        None => quote! { None },
+    }
+}
+
+pub(crate) fn generate_content_tree(
+    options: &Options,
+    trees: &[FsTree],
+    outputs: &HashMap<&PathBuf, Vec<PandocOutput>>,
+    documents: &HashMap<&PathBuf, Document>,
+) -> syn::Result<TokenStream> {
+    let mod_name = &options.mod_name;
+    let tree_name = &options.tree_name;
+    let (nodes, ctx, assets) = prepare(options, trees, outputs, documents)?;
+    let subtrees = trees
+        .iter()
+        .map(|i| process_tree_element(&i.tree, &ctx, &i.route))
+        .collect::<Vec<syn::Result<TokenStream>>>();
+    let subtrees = collect_results(subtrees)?;
+
+    let (metadata_field, metadata_definition) = match &options.metadata {
+        Some(schema) => {
+            let definition = schema.struct_definition();
+            (
+                quote! { , metadata: Metadata },
+                quote! {
+                    #definition
+
+                    /// Calendar date parsed from front matter (`YYYY-MM-DD`)
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+                    pub(crate) struct Date {
+                        pub(crate) year: i32,
+                        pub(crate) month: u8,
+                        pub(crate) day: u8
+                    }
+                },
+            )
+        }
+        None => (quote! {}, quote! {}),
+    };
+
+    let mut component = Vec::new();
+    let mut subtree_it = subtrees.into_iter();
+    for element in options.content.iter() {
+        match element {
+            Element::Special { ty } => component.push(quote! {
+                ContentTree::Special { ty: #ty }
+            }),
+
+            Element::CompileFromPath { .. } => {
+                let code = subtree_it.next().unwrap();
+
+                component.push(code);
+            }
+        }
+    }
+
+    let content = quote! {
+        vec![
+            #(#component),*
+        ]
+    };
+
+    let source_files = generate_source_files(trees);
+    // Re-expand when the configuration or an options file changes too
+    let config = options
+        .config
+        .iter()
+        .chain(trees.iter().flat_map(|t| t.options_files.iter()))
+        .map(|path| {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone()).display().to_string();
+            quote! { const _: &[u8] = include_bytes!(#path); }
+        });
+    let routes = generate_routes(options, &nodes);
+    let navigation = generate_navigation(&nodes);
+    let search = generate_search(options, &nodes, documents);
+    let site = generate_site(options, &nodes, documents)?;
+    let link_check = link_warnings(options, &nodes, &ctx, assets.as_ref())?
+        .iter()
+        .enumerate()
+        .map(|(i, (span, message))| warning(i, message, *span))
+        .collect::<TokenStream>();
+    let assets = assets.map(|assets| assets.generate());
+    let traversal = generate_traversal();
+
+    Ok(quote! {
+        // Not every accessor is used, and `include!` from a build script,
+        // unlike a macro expansion, does not silence dead code warnings
+        #[allow(dead_code)]
+        pub(crate) mod #mod_name {
+            use lazy_static::lazy_static;
+
+            #[derive(Debug, PartialEq, Eq)]
+            pub(crate) enum ContentTree {
+                Special {
+                    ty: &'static str
+                },
+
+                Html {
+                    name: &'static str,
+                    order: usize,
+                    content: &'static str,
+                    headings: &'static [Heading],
+                    outputs: &'static [(&'static str, Output)],
+                    route: Option<&'static str>,
+                    meta: Meta
+                    #metadata_field
+                },
+
+                Binary {
+                    name: &'static str,
+                    order: usize,
+                    bytes: &'static [u8],
+                    mime: &'static str,
+                    outputs: &'static [(&'static str, Output)],
+                    route: Option<&'static str>,
+                    meta: Meta
+                    #metadata_field
+                },
+
+                Nested {
+                    name: &'static str,
+                    elements: std::vec::Vec<ContentTree>,
+                    route: Option<&'static str>
+                }
+            }
+
+            #[allow(dead_code)]
+            impl ContentTree {
+                /// Rendering of a leaf in one of the requested output formats,
+                /// keyed by pandoc's name for it (`html5`, `plain`, `latex`, ...)
+                pub(crate) fn output(&self, format: &str) -> Option<&'static Output> {
+                    match self {
+                        ContentTree::Html { outputs, .. } | ContentTree::Binary { outputs, .. } => {
+                            outputs.iter().find(|(f, _)| *f == format).map(|(_, o)| o)
+                        }
+                        _ => None,
+                    }
+                }
+            }
+
+            /// Heading of a page, for building a table of contents
+            #[derive(Debug, PartialEq, Eq)]
+            pub(crate) struct Heading {
+                pub(crate) level: u8,
+                pub(crate) id: &'static str,
+                pub(crate) text: &'static str
+            }
+
+            /// One rendering of a source file
+            #[derive(Debug, PartialEq, Eq)]
+            pub(crate) enum Output {
+                Text(&'static str),
+                Binary {
+                    bytes: &'static [u8],
+                    mime: &'static str
+                }
+            }
+
+            /// Value from the document's YAML metadata block
+            #[derive(Debug, PartialEq, Eq)]
+            pub(crate) enum Meta {
+                String(&'static str),
+                Bool(bool),
+                List(&'static [Meta]),
+                Map(&'static [(&'static str, Meta)])
+            }
+
+            #[allow(dead_code)]
+            impl Meta {
+                pub(crate) fn get(&self, key: &str) -> Option<&Meta> {
+                    match self {
+                        Meta::Map(entries) => entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v),
+                        _ => None,
+                    }
+                }
+
+                pub(crate) fn as_str(&self) -> Option<&'static str> {
+                    match self {
+                        Meta::String(s) => Some(s),
+                        _ => None,
+                    }
+                }
+
+                pub(crate) fn as_bool(&self) -> Option<bool> {
+                    match self {
+                        Meta::Bool(b) => Some(*b),
+                        _ => None,
+                    }
+                }
+
+                pub(crate) fn as_list(&self) -> Option<&'static [Meta]> {
+                    match self {
+                        Meta::List(items) => Some(items),
+                        _ => None,
+                    }
+                }
+
+                pub(crate) fn as_map(&self) -> Option<&'static [(&'static str, Meta)]> {
+                    match self {
+                        Meta::Map(entries) => Some(entries),
+                        _ => None,
+                    }
+                }
+            }
+
+            #metadata_definition
+
+            #source_files
+
+            #(#config)*
+
+            #routes
+
+            #navigation
+
+            #search
+
+            #site
+
+            #link_check
+
+            #assets
+
+            #traversal
+
+            lazy_static! {
+                pub(crate) static ref #tree_name: ContentTree = ContentTree::Nested {
+                    name: "ROOT",
+                    elements: #content,
+                    route: None
+                };
+            }
+        }
+    })
+}
+
+/// Nodes in tree order and the state for emitting them, with links,
+/// inlined images and assets already resolved
+fn prepare<'a>(
+    options: &'a Options,
+    trees: &[FsTree],
+    outputs: &'a HashMap<&'a PathBuf, Vec<PandocOutput>>,
+    documents: &'a HashMap<&'a PathBuf, Document>,
+) -> syn::Result<(Vec<NodeInfo>, Context<'a>, Option<Assets>)> {
+    let nodes = collect_all_nodes(options, trees);
+    let mut ctx = Context {
+        rewrites: resolve_links(options, &nodes, documents)?,
+        next_order: Cell::new(0),
+        outputs,
+        documents,
+        schema: options.metadata.as_ref(),
+        toc_depth: options.toc_depth,
+        formats: ConversionSettings::for_each_format(options)
+            .iter()
+            .map(|settings| {
+                let mime = settings
+                    .output_format
+                    .as_ref()
+                    .map_or("application/octet-stream", |(fmt, _)| mime_type(fmt));
+                (settings.format_name(), mime)
+            })
+            .collect(),
+    };
+    if let Some(limit) = options.inline_assets_below {
+        inline_images(options, &nodes, &mut ctx, limit)?;
+    }
+    let assets = match &options.assets {
+        Some(_) => Some(collect_assets(options, trees, &nodes, &mut ctx)?),
+        None => None,
+    };
+
+    Ok((nodes, ctx, assets))
+}
+
+/// List every source file through `include_bytes!`, so rustc records them
+/// as dependencies and re-expands the macro when one of them changes.
+fn generate_source_files(trees: &[FsTree]) -> TokenStream {
+    let files = trees
+        .iter()
+        .flat_map(|t| t.get_all_src_files())
+        .map(|p| fs::canonicalize(&p).unwrap_or(p).display().to_string())
+        .map(|p| quote! { (#p, include_bytes!(#p)) });
+
+    quote! {
+        /// Every source file the tree was compiled from, with its contents
+        #[allow(dead_code)]
+        pub(crate) const SOURCE_FILES: &[(&str, &[u8])] = &[
+            #(#files),*
+        ];
+    }
+}
+
+/// Iteration helpers and the `Visitor` trait on the generated tree
+fn generate_traversal() -> TokenStream {
+    quote! {
+        #[allow(dead_code)]
+        impl ContentTree {
+            /// Child nodes of a `Nested` node, empty for every other node
+            pub(crate) fn children(&self) -> &[ContentTree] {
+                match self {
+                    ContentTree::Nested { elements, .. } => elements,
+                    _ => &[],
+                }
+            }
+
+            /// This node and all its descendants, depth-first in tree order
+            pub(crate) fn iter(&self) -> Iter<'_> {
+                Iter { stack: vec![(self, 0)] }
+            }
+
+            /// `Html` and `Binary` nodes below this one, in tree order
+            pub(crate) fn leaves(&self) -> impl Iterator<Item = &ContentTree> {
+                self.iter()
+                    .filter(|node| matches!(node, ContentTree::Html { .. } | ContentTree::Binary { .. }))
+            }
+
+            /// Like `iter`, paired with each node's depth below this one
+            pub(crate) fn walk_with_depth(&self) -> impl Iterator<Item = (&ContentTree, usize)> {
+                let mut iter = self.iter();
+                std::iter::from_fn(move || {
+                    let depth = iter.stack.last()?.1;
+                    iter.next().map(|node| (node, depth))
+                })
+            }
+
+            /// Parent of `node` within this tree, nodes are compared by address
+            pub(crate) fn parent_of(&self, node: &ContentTree) -> Option<&ContentTree> {
+                self.iter()
+                    .find(|parent| parent.children().iter().any(|child| std::ptr::eq(child, node)))
+            }
+
+            /// Nodes from this one down to `node`, both included
+            pub(crate) fn path_to(&self, node: &ContentTree) -> Option<Vec<&ContentTree>> {
+                if std::ptr::eq(self, node) {
+                    return Some(vec![self]);
+                }
+
+                self.children().iter().find_map(|child| {
+                    let mut path = child.path_to(node)?;
+                    path.insert(0, self);
+                    Some(path)
+                })
+            }
+
+            /// Call the `visitor` method matching this node's variant
+            pub(crate) fn accept<'a, V: Visitor<'a> + ?Sized>(&'a self, visitor: &mut V) {
+                match self {
+                    ContentTree::Special { .. } => visitor.visit_special(self),
+                    ContentTree::Html { .. } => visitor.visit_html(self),
+                    ContentTree::Binary { .. } => visitor.visit_binary(self),
+                    ContentTree::Nested { .. } => visitor.visit_nested(self),
+                }
+            }
+        }
+
+        /// Depth-first iterator returned by `ContentTree::iter`
+        pub(crate) struct Iter<'a> {
+            stack: std::vec::Vec<(&'a ContentTree, usize)>,
+        }
+
+        impl<'a> Iterator for Iter<'a> {
+            type Item = &'a ContentTree;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let (node, depth) = self.stack.pop()?;
+                self.stack
+                    .extend(node.children().iter().rev().map(|child| (child, depth + 1)));
+                Some(node)
+            }
+        }
+
+        /// Callbacks for `ContentTree::accept`, every method defaults to doing
+        /// nothing except `visit_nested`, which visits the children in order
+        #[allow(dead_code)]
+        pub(crate) trait Visitor<'a> {
+            fn visit_special(&mut self, _node: &'a ContentTree) {}
+
+            fn visit_html(&mut self, _node: &'a ContentTree) {}
+
+            fn visit_binary(&mut self, _node: &'a ContentTree) {}
+
+            fn visit_nested(&mut self, node: &'a ContentTree) {
+                for child in node.children() {
+                    child.accept(self);
+                }
+            }
+        }
+    }
+}
+
+/// Shared state for walking the file trees
+struct Context<'a> {
+    /// Reading-order index of the next leaf, leaves are generated in tree order
+    next_order: Cell<usize>,
+    outputs: &'a HashMap<&'a PathBuf, Vec<PandocOutput>>,
+    documents: &'a HashMap<&'a PathBuf, Document>,
+    schema: Option<&'a MetadataSchema>,
+    toc_depth: u8,
+    /// Name and MIME type of every output format, primary first
+    formats: Vec<(String, &'static str)>,
+    /// Links of every source to replace in its text outputs
+    rewrites: HashMap<&'a PathBuf, Vec<(String, String)>>,
+}
+
+/// Collect every result, combining all errors into one
+fn collect_results<T>(results: Vec<syn::Result<T>>) -> syn::Result<Vec<T>> {
+    let mut out = Vec::new();
+    let mut errors = Errors::default();
+
+    for result in results {
+        match result {
+            Ok(code) => out.push(code),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    errors.finish().map(|_| out)
+}
+
+fn get_name(path: &Path) -> String {
+    // Get the file name as &str (panic if missing or invalid)
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .expect("Invalid or missing file name");
+
+    // Remove the extension (mandatory)
+    let name_without_extension = file_name
+        .split('.')
+        .next()
+        .expect("Missing name before extension");
+
+    // Extract part after the first '-' if present
+    match name_without_extension.split_once('-') {
+        Some((_prefix, middle)) => middle.to_string(),
+        None => name_without_extension.to_string(), // No dash found
+    }
+}
+
+fn get_mod_name(path: &Path) -> String {
+    // For ModFile, the name comes from the parent directory
+    let parent = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|s| s.to_str())
+        .expect("Invalid or missing parent directory name");
+
+    match parent.split_once('-') {
+        Some((_prefix, middle)) => middle.to_string(),
+        None => parent.to_string(),
+    }
+}
+
+fn process_leaf(
+    path: &PathBuf,
+    options: &SourceOptions,
+    name: String,
+    ctx: &Context,
+    route: &TokenStream,
+) -> syn::Result<TokenStream> {
+    let document = ctx.documents.get(path).unwrap();
+    let meta = &document.meta;
+    let headings = page_headings(document, ctx, options.id_prefix());
+    let metadata = match ctx.schema {
+        Some(schema) => {
+            let metadata = schema.instantiate(meta, &path.display().to_string())?;
+            quote! { , metadata: #metadata }
+        }
+        None => quote! {},
+    };
+
+    // The primary rendering is bound to a constant, so it is shared with
+    // its entry in `outputs` instead of being embedded twice
+    let rewrites = ctx.rewrites.get(path).map_or(&[][..], Vec::as_slice);
+    let mut outputs = Vec::new();
+    for (i, (output, (format, mime))) in ctx.outputs.get(path).unwrap().iter().zip(&ctx.formats).enumerate() {
+        let value = if i == 0 {
+            quote! { PRIMARY }
+        } else {
+            output_literal(output, rewrites)
+        };
+
+        outputs.push(match output {
+            PandocOutput::ToBuffer(_) => quote! { (#format, Output::Text(#value)) },
+            _ => quote! { (#format, Output::Binary { bytes: #value, mime: #mime }) },
+        });
+    }
+
+    let primary = &ctx.outputs.get(path).unwrap()[0];
+    let primary_literal = output_literal(primary, rewrites);
+    let order = ctx.next_order.replace(ctx.next_order.get() + 1);
+
+    Ok(match primary {
+        PandocOutput::ToBuffer(_) => quote! {
+            {
+                const PRIMARY: &str = #primary_literal;
+                ContentTree::Html {
+                    name: #name,
+                    order: #order,
+                    content: PRIMARY,
+                    headings: &[#(#headings),*],
+                    outputs: &[#(#outputs),*],
+                    route: #route,
+                    meta: #meta
+                    #metadata
+                }
+            }
+        },
+
+        _ => {
+            let mime = ctx.formats[0].1;
+
+            quote! {
+                {
+                    const PRIMARY: &[u8] = #primary_literal;
+                    ContentTree::Binary {
+                        name: #name,
+                        order: #order,
+                        bytes: PRIMARY,
+                        mime: #mime,
+                        outputs: &[#(#outputs),*],
+                        route: #route,
+                        meta: #meta
+                        #metadata
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Headings down to `toc_depth`, with ids as the HTML writer prefixes them
+fn page_headings(document: &Document, ctx: &Context, id_prefix: &str) -> Vec<Heading> {
+    document
+        .headings
+        .iter()
+        .filter(|h| h.level <= ctx.toc_depth)
+        .map(|h| match h.id.as_str() {
+            "" => h.clone(),
+            id => Heading {
+                id: format!("{}{}", id_prefix, id),
+                ..h.clone()
+            },
+        })
+        .collect()
+}
+
+/// String or byte string literal holding a rendering
+fn output_literal(output: &PandocOutput, rewrites: &[(String, String)]) -> TokenStream {
+    match output {
+        PandocOutput::ToBuffer(text) => rewrite_urls(text, rewrites).to_token_stream(),
+        PandocOutput::ToBufferRaw(bytes) => syn::LitByteStr::new(bytes, Span::call_site()).to_token_stream(),
+        PandocOutput::ToFile(_) => unreachable!("conversions are always read back into memory"),
+    }
+}
+
+fn process_tree_element(
+    tree: &TreeElement,
+    ctx: &Context,
+    route: &Option<String>,
+) -> syn::Result<TokenStream> {
+    let name = node_name(tree);
+    let route_tokens = generate_option(route);
+    match tree {
+        TreeElement::File(path, options) | TreeElement::ModFile(path, options) => {
+            process_leaf(path, options, name, ctx, &route_tokens)
+        }
+
+        TreeElement::Nested(_, subtree) => {
+            let content = process_subtree_elements(subtree, ctx, route)?;
+
+            Ok(quote! {
+                ContentTree::Nested {
+                    name: #name,
+                    elements: #content,
+                    route: #route_tokens
+                }
+            })
+        }
+    }
+}
+
+fn process_subtree_elements(
+    tree: &[TreeElement],
+    ctx: &Context,
+    parent_route: &Option<String>,
+) -> syn::Result<TokenStream> {
+    let components = tree
+        .iter()
+        .zip(child_routes(parent_route, tree))
+        .map(|(i, route)| process_tree_element(i, ctx, &route))
+        .collect::<Vec<syn::Result<TokenStream>>>();
+    let components = collect_results(components)?;
+
+    Ok(quote! {
+        vec![
+            #(#components),*
+        ]
+    })
+}
+
+fn node_name(tree: &TreeElement) -> String {
+    match tree {
+        TreeElement::File(path, _) | TreeElement::Nested(path, _) => get_name(path),
+        TreeElement::ModFile(path, _) => get_mod_name(path),
+    }
+}
+
+/// Full route of a child node: its parent's route followed by its slug
+fn child_route(parent: &Option<String>, slug: &str) -> Option<String> {
+    parent
+        .as_ref()
+        .map(|parent| format!("{}/{}", parent.trim_end_matches('/'), slug))
+}
+
+/// Routes of the children of a node at `parent`. Siblings sharing a slug,
+/// such as `01-section` and `02-section`, get `-2`, `-3`... appended in
+/// tree order.
+fn child_routes(parent: &Option<String>, children: &[TreeElement]) -> Vec<Option<String>> {
+    let mut used = HashSet::new();
+    children
+        .iter()
+        .map(|child| {
+            let name = node_name(child);
+            let mut slug = name.clone();
+            let mut n = 1;
+            while !used.insert(slug.clone()) {
+                n += 1;
+                slug = format!("{}-{}", name, n);
+            }
+            child_route(parent, &slug)
+        })
+        .collect()
+}
+
+/// Drop trailing slashes, keeping `/` itself
+fn normalize_route(route: &str) -> &str {
+    match route.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Position of one generated node, gathered in tree order
+#[derive(Debug, PartialEq)]
+struct NodeInfo {
+    /// Indices leading to the node from ROOT
+    index: Vec<usize>,
+    route: Option<String>,
+    /// Name and route of every node from the top-level one down to this one
+    crumbs: Vec<(String, Option<String>)>,
+    /// Source file of a leaf
+    source: Option<PathBuf>,
+    /// Prefix the heading ids of a leaf's page get from `IdPrefix`
+    id_prefix: String,
+}
+
+/// Record `tree` and its descendants, depth-first like the generated code
+fn collect_nodes(
+    tree: &TreeElement,
+    route: &Option<String>,
+    index: &mut Vec<usize>,
+    crumbs: &mut Vec<(String, Option<String>)>,
+    out: &mut Vec<NodeInfo>,
+) {
+    crumbs.push((node_name(tree), route.clone()));
+    out.push(NodeInfo {
+        index: index.clone(),
+        route: route.clone(),
+        crumbs: crumbs.clone(),
+        source: match tree {
+            TreeElement::File(path, _) | TreeElement::ModFile(path, _) => Some(path.clone()),
+            TreeElement::Nested(..) => None,
+        },
+        id_prefix: match tree {
+            TreeElement::File(_, options) | TreeElement::ModFile(_, options) => options.id_prefix().to_string(),
+            TreeElement::Nested(..) => String::new(),
+        },
+    });
+
+    if let TreeElement::Nested(_, subtree) = tree {
+        for (i, (child, child_route)) in subtree.iter().zip(child_routes(route, subtree)).enumerate() {
+            index.push(i);
+            collect_nodes(child, &child_route, index, crumbs, out);
+            index.pop();
+        }
+    }
+
+    crumbs.pop();
+}
+
+fn collect_all_nodes(options: &Options, trees: &[FsTree]) -> Vec<NodeInfo> {
+    let mut nodes = Vec::new();
+    let mut tree_it = trees.iter();
+
+    for (i, element) in options.content.iter().enumerate() {
+        if let Element::CompileFromPath { .. } = element {
+            let tree = tree_it.next().unwrap();
+            collect_nodes(&tree.tree, &tree.route, &mut vec![i], &mut Vec::new(), &mut nodes);
+        }
+    }
+
+    nodes
+}
+
+/// Sorted route table and the lookup function built on it
+fn generate_routes(options: &Options, nodes: &[NodeInfo]) -> TokenStream {
+    let tree_name = &options.tree_name;
+    let mut routes: Vec<(&str, &[usize])> = nodes
+        .iter()
+        .filter_map(|node| Some((normalize_route(node.route.as_ref()?), node.index.as_slice())))
+        .collect();
+
+    // Stable sort, so the first node in tree order wins a route given to
+    // several content elements
+    routes.sort_by(|a, b| a.0.cmp(b.0));
+    routes.dedup_by(|b, a| a.0 == b.0);
+
+    let entries = routes.iter().map(|(route, index)| quote! { (#route, &[#(#index),*]) });
+
+    quote! {
+        /// Full route of every routed node with its index path from the root,
+        /// sorted by route
+        #[allow(dead_code)]
+        pub(crate) const ROUTES: &[(&str, &[usize])] = &[
+            #(#entries),*
+        ];
+
+        #[allow(dead_code)]
+        impl ContentTree {
+            /// Look a node up by its full route, ignoring trailing slashes
+            pub(crate) fn find_by_route(route: &str) -> Option<&'static ContentTree> {
+                let route = match route.trim_end_matches('/') {
+                    "" => "/",
+                    trimmed => trimmed,
+                };
+                let i = ROUTES.binary_search_by(|(r, _)| (*r).cmp(route)).ok()?;
+                Self::node_at(ROUTES[i].1)
+            }
+
+            /// Follow an index path down from the root
+            fn node_at(index: &[usize]) -> Option<&'static ContentTree> {
+                let mut node: &'static ContentTree = &#tree_name;
+                for &i in index {
+                    node = node.children().get(i)?;
+                }
+                Some(node)
+            }
+        }
+    }
+}
+
+/// Span of the content element at `index`, for errors about its pages
+fn element_span(options: &Options, index: usize) -> Span {
+    match &options.content[index] {
+        Element::CompileFromPath { span, .. } => *span,
+        Element::Special { .. } => Span::call_site(),
+    }
+}
+
+/// Routes replacing the links between source files, failing on links to
+/// files outside the content trees or to missing headings
+fn resolve_links<'a>(
+    options: &Options,
+    nodes: &[NodeInfo],
+    documents: &HashMap<&'a PathBuf, Document>,
+) -> syn::Result<HashMap<&'a PathBuf, Vec<(String, String)>>> {
+    let resolver = LinkResolver::new(nodes.iter().filter_map(|node| {
+        let source = node.source.as_ref()?;
+        let headings = &documents.get(source)?.headings;
+        let ids = headings
+            .iter()
+            .filter(|h| !h.id.is_empty())
+            .map(|h| format!("{}{}", node.id_prefix, h.id))
+            .collect();
+        let target = LinkTarget {
+            route: node.route.as_deref().map(|route| normalize_route(route).to_string()),
+            ids,
+        };
+        Some((source.clone(), target))
+    }));
+
+    let mut errors = Errors::default();
+    let mut rewrites = HashMap::new();
+    for node in nodes {
+        let Some((source, document)) = node.source.as_ref().and_then(|s| documents.get_key_value(s)) else {
+            continue;
+        };
+        if rewrites.contains_key(source) {
+            // Reached through another content element
+            continue;
+        }
+
+        let mut replaced = Vec::new();
+        for link in &document.links {
+            match resolver.resolve(source, link) {
+                Ok(Some(route)) => replaced.push((link.clone(), route)),
+                Ok(None) => {}
+                Err(e) => errors.push(syn::Error::new(
+                    element_span(options, node.index[0]),
+                    format!("{}: {}", source.display(), e),
+                )),
+            }
+        }
+        rewrites.insert(*source, replaced);
+    }
+
+    errors.finish()?;
+    Ok(rewrites)
+}
+
+/// HTML outputs of `source` with links already rewritten
+fn html_outputs(ctx: &Context, source: &PathBuf) -> Vec<String> {
+    let rewrites = ctx.rewrites.get(source).map_or(&[][..], Vec::as_slice);
+
+    ctx.outputs
+        .get(source)
+        .into_iter()
+        .flatten()
+        .zip(&ctx.formats)
+        .filter_map(|(output, (format, _))| match output {
+            PandocOutput::ToBuffer(text) if format.starts_with("html") => Some(rewrite_urls(text, rewrites)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replace images smaller than `limit` bytes with data URIs
+fn inline_images(options: &Options, nodes: &[NodeInfo], ctx: &mut Context, limit: u64) -> syn::Result<()> {
+    let mut errors = Errors::default();
+    let mut rewrites: Vec<(&PathBuf, Vec<(String, String)>)> = Vec::new();
+
+    for node in nodes {
+        let Some(source) = node.source.as_ref().and_then(|s| ctx.outputs.get_key_value(s)).map(|(s, _)| *s) else {
+            continue;
+        };
+        if rewrites.iter().any(|(s, _)| *s == source) {
+            continue;
+        }
+
+        let mut replaced: Vec<(String, String)> = Vec::new();
+        for html in html_outputs(ctx, source) {
+            for url in local_urls(&html) {
+                if url.tag != "img" || url.attribute != "src" || replaced.iter().any(|(raw, _)| *raw == url.raw) {
+                    continue;
+                }
+                let Some(file) = resolve_file(source, &url.path) else {
+                    continue;
+                };
+                if fs::metadata(&file).map_or(true, |meta| meta.len() >= limit) {
+                    continue;
+                }
+
+                match fs::read(&file) {
+                    Ok(contents) => replaced.push((url.raw, data_uri(&file, &contents))),
+                    Err(e) => errors.push(syn::Error::new(
+                        element_span(options, node.index[0]),
+                        format!("{}: failed to read image {}: {}", source.display(), file.display(), e),
+                    )),
+                }
+            }
+        }
+        rewrites.push((source, replaced));
+    }
+
+    errors.finish()?;
+    for (source, replaced) in rewrites {
+        ctx.rewrites.entry(source).or_default().extend(replaced);
+    }
+    Ok(())
+}
+
+/// Embed the asset files of every tree and the local files pages refer to,
+/// pointing the pages at the asset routes
+fn collect_assets(options: &Options, trees: &[FsTree], nodes: &[NodeInfo], ctx: &mut Context) -> syn::Result<Assets> {
+    let mut errors = Errors::default();
+    let mut assets = Assets::default();
+
+    let spans = options.content.iter().filter_map(|element| match element {
+        Element::CompileFromPath { span, .. } => Some(*span),
+        Element::Special { .. } => None,
+    });
+    for (tree, span) in trees.iter().zip(spans) {
+        for path in &tree.assets {
+            if let Err(e) = assets.add(path) {
+                errors.push(syn::Error::new(span, format!("Failed to read asset {}: {}", path.display(), e)));
+            }
+        }
+    }
+
+    let pages: HashSet<PathBuf> = nodes
+        .iter()
+        .filter_map(|node| fs::canonicalize(node.source.as_ref()?).ok())
+        .collect();
+
+    let mut rewrites: Vec<(&PathBuf, Vec<(String, String)>)> = Vec::new();
+    for node in nodes {
+        let Some(source) = node.source.as_ref().and_then(|s| ctx.outputs.get_key_value(s)).map(|(s, _)| *s) else {
+            continue;
+        };
+        if rewrites.iter().any(|(s, _)| *s == source) {
+            // Reached through another content element
+            continue;
+        }
+
+        let mut replaced: Vec<(String, String)> = Vec::new();
+        for html in html_outputs(ctx, source) {
+            for url in local_urls(&html) {
+                let Some(file) = resolve_file(source, &url.path) else {
+                    continue;
+                };
+                if replaced.iter().any(|(raw, _)| *raw == url.raw)
+                    || fs::canonicalize(&file).is_ok_and(|file| pages.contains(&file))
+                {
+                    continue;
+                }
+
+                match assets.add(&file) {
+                    Ok(asset) => {
+                        let suffix = url.raw.find(['#', '?']).map_or("", |i| &url.raw[i..]);
+                        replaced.push((url.raw.clone(), format!("{}{}", asset.route, suffix)));
+                    }
+                    Err(e) => errors.push(syn::Error::new(
+                        element_span(options, node.index[0]),
+                        format!("{}: failed to read asset {}: {}", source.display(), file.display(), e),
+                    )),
+                }
+            }
+        }
+        rewrites.push((source, replaced));
+    }
+
+    errors.finish()?;
+    for (source, replaced) in rewrites {
+        ctx.rewrites.entry(source).or_default().extend(replaced);
+    }
+    Ok(assets)
+}
+
+/// Verify local `href` and `src` targets of every HTML output, failing
+/// when `check_links` denies broken ones and returning them as warnings
+/// otherwise
+fn link_warnings(
+    options: &Options,
+    nodes: &[NodeInfo],
+    ctx: &Context,
+    assets: Option<&Assets>,
+) -> syn::Result<Vec<(Span, String)>> {
+    if options.check_links == LinkCheck::Allow {
+        return Ok(Vec::new());
+    }
+
+    let routes: HashSet<String> = nodes
+        .iter()
+        .filter_map(|node| Some(normalize_route(node.route.as_ref()?).to_string()))
+        .chain(assets.into_iter().flat_map(Assets::routes).map(str::to_string))
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut checked = HashSet::new();
+    for node in nodes {
+        let Some(source) = &node.source else {
+            continue;
+        };
+        if !checked.insert(source) {
+            continue;
+        }
+
+        let mut broken = Vec::new();
+        for html in html_outputs(ctx, source) {
+            for url in local_urls(&html) {
+                let problem = (url.attribute, url.path);
+                if !check_url(source, node.route.as_deref(), &problem.1, &routes) && !broken.contains(&problem) {
+                    broken.push(problem);
+                }
+            }
+        }
+
+        for (attribute, url) in broken {
+            let what = if attribute == "src" { "missing asset" } else { "broken link" };
+            problems.push((
+                element_span(options, node.index[0]),
+                format!("{}: {} `{}`", source.display(), what, url),
+            ));
+        }
+    }
+
+    match options.check_links {
+        LinkCheck::Deny => {
+            let mut errors = Errors::default();
+            for (span, message) in problems {
+                errors.push(syn::Error::new(span, message));
+            }
+            errors.finish()?;
+            Ok(Vec::new())
+        }
+        _ => Ok(problems),
+    }
+}
+
+/// Reading order and breadcrumbs of every leaf
+fn generate_navigation(nodes: &[NodeInfo]) -> TokenStream {
+    let leaves = nodes.iter().filter(|node| node.source.is_some());
+    let order = leaves.clone().map(|node| {
+        let index = &node.index;
+        quote! { &[#(#index),*] }
+    });
+    let crumbs = leaves.map(|node| {
+        let crumbs = node.crumbs.iter().map(|(name, route)| {
+            let route = generate_option(route);
+            quote! { (#name, #route) }
+        });
+        quote! { &[#(#crumbs),*] }
+    });
+
+    quote! {
+        /// Index path of every `Html` and `Binary` leaf, in reading order
+        #[allow(dead_code)]
+        pub(crate) const READING_ORDER: &[&[usize]] = &[
+            #(#order),*
+        ];
+
+        /// Breadcrumbs of every leaf in reading order: name and route of each
+        /// node from the top-level content node down to the leaf itself
+        #[allow(dead_code)]
+        pub(crate) const BREADCRUMBS: &[&[(&str, Option<&str>)]] = &[
+            #(#crumbs),*
+        ];
+
+        #[allow(dead_code)]
+        impl ContentTree {
+            /// Position of a leaf in reading order
+            pub(crate) fn order(&self) -> Option<usize> {
+                match self {
+                    ContentTree::Html { order, .. } | ContentTree::Binary { order, .. } => Some(*order),
+                    _ => None,
+                }
+            }
+
+            /// Leaf read before this one
+            pub(crate) fn prev(&self) -> Option<&'static ContentTree> {
+                Self::node_at(READING_ORDER[self.order()?.checked_sub(1)?])
+            }
+
+            /// Leaf read after this one
+            pub(crate) fn next(&self) -> Option<&'static ContentTree> {
+                Self::node_at(READING_ORDER.get(self.order()? + 1)?)
+            }
+
+            /// Name and route of every node from the top-level content node
+            /// down to this leaf, empty for other nodes
+            pub(crate) fn breadcrumbs(&self) -> &'static [(&'static str, Option<&'static str>)] {
+                self.order().map_or(&[], |order| BREADCRUMBS[order])
+            }
+        }
+    }
+}
+
+/// Top-level front matter entry of a document
+fn meta_entry<'a>(document: &'a Document, key: &str) -> Option<&'a MetaValue> {
+    match &document.meta {
+        MetaValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+/// Front matter `title`, or the node name
+fn page_title(node: &NodeInfo, document: &Document) -> String {
+    match meta_entry(document, "title") {
+        Some(MetaValue::String(title)) => title.clone(),
+        _ => node.crumbs.last().unwrap().0.clone(),
+    }
+}
+
+/// Search index over every leaf, in reading order
+fn generate_search(
+    options: &Options,
+    nodes: &[NodeInfo],
+    documents: &HashMap<&PathBuf, Document>,
+) -> TokenStream {
+    if !options.search_index && !options.search_json {
+        return quote! {};
+    }
+
+    let pages = search_documents(nodes, documents);
+    let index = SearchIndex::build(&pages);
+    let code = if options.search_index { index.generate() } else { quote! {} };
+    let json = if options.search_json {
+        let json = index.to_elasticlunr_json(&pages);
+        quote! {
+            /// The search index serialized for elasticlunr's `Index.load`
+            #[allow(dead_code)]
+            pub(crate) const SEARCH_INDEX_JSON: &str = #json;
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #code
+        #json
+    }
+}
+
+/// Every leaf as the search index sees it, in reading order
+fn search_documents(nodes: &[NodeInfo], documents: &HashMap<&PathBuf, Document>) -> Vec<SearchDocument> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let document = documents.get(node.source.as_ref()?)?;
+
+            Some(SearchDocument {
+                title: page_title(node, document),
+                route: node.route.clone(),
+                text: document.text.clone(),
+            })
+        })
+        .collect()
+}
+
+/// `SITEMAP_XML` and `FEED_ATOM`
+fn generate_site(
+    options: &Options,
+    nodes: &[NodeInfo],
+    documents: &HashMap<&PathBuf, Document>,
+) -> syn::Result<TokenStream> {
+    let (sitemap, feed) = site_xml(options, nodes, documents)?;
+    let sitemap = sitemap.map(|xml| {
+        quote! {
+            /// `sitemap.xml` listing every routed page
+            #[allow(dead_code)]
+            pub(crate) const SITEMAP_XML: &str = #xml;
+        }
+    });
+    let feed = feed.map(|xml| {
+        quote! {
+            /// Atom feed of the newest dated pages
+            #[allow(dead_code)]
+            pub(crate) const FEED_ATOM: &str = #xml;
+        }
+    });
+
+    Ok(quote! {
+        #sitemap
+        #feed
+    })
+}
+
+/// Sitemap and Atom feed over the routed leaves, skipping drafts, when
+/// they are enabled
+fn site_xml(
+    options: &Options,
+    nodes: &[NodeInfo],
+    documents: &HashMap<&PathBuf, Document>,
+) -> syn::Result<(Option<String>, Option<String>)> {
+    let Some(base_url) = &options.base_url else {
+        return Ok((None, None));
+    };
+
+    let mut errors = Errors::default();
+    let mut pages: Vec<SitePage> = Vec::new();
+    for node in nodes {
+        let (Some(source), Some(route)) = (&node.source, &node.route) else {
+            continue;
+        };
+        let Some(document) = documents.get(source) else {
+            continue;
+        };
+        let route = normalize_route(route);
+        if matches!(meta_entry(document, "draft"), Some(MetaValue::Bool(true)))
+            || pages.iter().any(|page| page.route == route)
+        {
+            continue;
+        }
+
+        let element = node.index[0];
+        let date = match meta_entry(document, "date") {
+            Some(MetaValue::String(date)) => match parse_date(date) {
+                Ok(date) => Some(date),
+                Err(e) => {
+                    errors.push(syn::Error::new(
+                        element_span(options, element),
+                        format!("{}: {}", source.display(), e),
+                    ));
+                    None
+                }
+            },
+            _ => None,
+        };
+        let summary = match meta_entry(document, "summary").or_else(|| meta_entry(document, "description")) {
+            Some(MetaValue::String(summary)) => summary.clone(),
+            _ => summarize(&document.text, 200),
+        };
+
+        pages.push(SitePage {
+            route: route.to_string(),
+            title: page_title(node, document),
+            date,
+            summary,
+            element,
+        });
+    }
+
+    // Feed sources must name one of the compile_from_path elements
+    let mut feed_elements = Vec::new();
+    for from in options.feed.iter().flat_map(|feed| &feed.from) {
+        let found = options.content.iter().position(|element| {
+            matches!(element, Element::CompileFromPath { path, .. } if *path == from.value())
+        });
+        match found {
+            Some(i) => feed_elements.push(i),
+            None => errors.push(syn::Error::new(
+                from.span(),
+                format!("feed source `{}` is not a compile_from_path path", from.value()),
+            )),
+        }
+    }
+    errors.finish()?;
+
+    let sitemap = options.sitemap.then(|| sitemap_xml(base_url, &pages));
+    let feed = options.feed.as_ref().map(|feed| {
+        pages.retain(|page| feed_elements.is_empty() || feed_elements.contains(&page.element));
+        atom_feed(base_url, feed, &pages)
+    });
+
+    Ok((sitemap, feed))
+}
+
+/// Render the trees to files laid out by route instead of a module, with a
+/// manifest mirroring `ContentTree`
+pub(crate) fn export_content_tree(
+    options: &Options,
+    trees: &[FsTree],
+    outputs: &HashMap<&PathBuf, Vec<PandocOutput>>,
+    documents: &HashMap<&PathBuf, Document>,
+) -> syn::Result<Export> {
+    let (nodes, ctx, assets) = prepare(options, trees, outputs, documents)?;
+    let warnings = link_warnings(options, &nodes, &ctx, assets.as_ref())?;
+    let mut export = Export {
+        files: BTreeMap::new(),
+        manifest: Value::Null,
+        warnings: warnings.into_iter().map(|(_, message)| message).collect(),
+    };
+
+    let mut claimed = HashSet::new();
+    let mut elements = Vec::new();
+    let mut tree_it = trees.iter();
+    for element in options.content.iter() {
+        elements.push(match element {
+            Element::Special { ty } => Ok(json!({ "kind": "special", "ty": ty })),
+            Element::CompileFromPath { span, .. } => {
+                let tree = tree_it.next().unwrap();
+                let mut pages = PageFiles { export: &mut export, claimed: &mut claimed, span: *span };
+                export_tree_element(&tree.tree, &ctx, &tree.route, &mut pages)
+            }
+        });
+    }
+    let elements = collect_results(elements)?;
+
+    let mut exported_assets = Vec::new();
+    for (path, asset) in assets.iter().flat_map(Assets::files) {
+        let file = route_dir(&asset.route).map_err(|e| syn::Error::new(Span::call_site(), e))?;
+        let contents = fs::read(path).map_err(|e| {
+            syn::Error::new(Span::call_site(), format!("Failed to read asset {}: {}", path.display(), e))
+        })?;
+        export.files.insert(file.clone(), contents);
+        exported_assets.push(json!({ "route": asset.route, "file": file, "mime": asset.mime }));
+    }
+
+    let (sitemap, feed) = site_xml(options, &nodes, documents)?;
+    let search = options.search_json.then(|| {
+        let pages = search_documents(&nodes, documents);
+        SearchIndex::build(&pages).to_elasticlunr_json(&pages)
+    });
+    let mut site_file = |name: &str, contents: Option<String>| {
+        let contents = contents?;
+        export.files.insert(name.to_string(), contents.into_bytes());
+        Some(name.to_string())
+    };
+    let (sitemap, feed, search) = (
+        site_file("sitemap.xml", sitemap),
+        site_file("feed.xml", feed),
+        site_file("search_index.json", search),
+    );
+
+    export.manifest = json!({
+        "tree": { "kind": "nested", "name": "ROOT", "route": null, "elements": elements },
+        "assets": exported_assets,
+        "sitemap": sitemap,
+        "feed": feed,
+        "search_index": search,
+    });
+    Ok(export)
+}
+
+/// Where the pages of one content element go
+struct PageFiles<'a> {
+    export: &'a mut Export,
+    /// Route directories already written, the first page in tree order
+    /// keeps a duplicated route like in `ROUTES`
+    claimed: &'a mut HashSet<String>,
+    span: Span,
+}
+
+/// Manifest entry of `tree`, writing the renderings of its leaves
+fn export_tree_element(
+    tree: &TreeElement,
+    ctx: &Context,
+    route: &Option<String>,
+    pages: &mut PageFiles,
+) -> syn::Result<Value> {
+    let name = node_name(tree);
+    match tree {
+        TreeElement::File(path, options) | TreeElement::ModFile(path, options) => {
+            export_leaf(path, options, name, ctx, route, pages)
+        }
+
+        TreeElement::Nested(_, subtree) => {
+            let elements = subtree
+                .iter()
+                .zip(child_routes(route, subtree))
+                .map(|(child, route)| export_tree_element(child, ctx, &route, pages))
+                .collect();
+
+            Ok(json!({
+                "kind": "nested",
+                "name": name,
+                "route": route,
+                "elements": collect_results(elements)?,
+            }))
+        }
+    }
+}
+
+fn export_leaf(
+    path: &PathBuf,
+    options: &SourceOptions,
+    name: String,
+    ctx: &Context,
+    route: &Option<String>,
+    pages: &mut PageFiles,
+) -> syn::Result<Value> {
+    let document = ctx.documents.get(path).unwrap();
+    if let Some(schema) = ctx.schema {
+        // Same front matter errors as the generated module
+        schema.instantiate(&document.meta, &path.display().to_string())?;
+    }
+
+    let dir = match route {
+        Some(route) => Some(route_dir(route).map_err(|e| syn::Error::new(pages.span, e))?),
+        None => None,
+    };
+    let dir = dir.filter(|dir| pages.claimed.insert(dir.clone()));
+
+    let rewrites = ctx.rewrites.get(path).map_or(&[][..], Vec::as_slice);
+    let renderings = ctx.outputs.get(path).unwrap();
+    let mut files = Map::new();
+    for (output, (format, _)) in renderings.iter().zip(&ctx.formats) {
+        let Some(dir) = &dir else {
+            break;
+        };
+        let contents = match output {
+            PandocOutput::ToBuffer(text) => rewrite_urls(text, rewrites).into_bytes(),
+            PandocOutput::ToBufferRaw(bytes) => bytes.clone(),
+            PandocOutput::ToFile(_) => unreachable!("conversions are always read back into memory"),
+        };
+        let file = page_file(dir, format, |file| pages.export.files.contains_key(file));
+        pages.export.files.insert(file.clone(), contents);
+        files.insert(format.clone(), Value::String(file));
+    }
+
+    let headings: Vec<Value> = page_headings(document, ctx, options.id_prefix())
+        .iter()
+        .map(|h| json!({ "level": h.level, "id": h.id, "text": h.text }))
+        .collect();
+    let mut node = json!({
+        "kind": "html",
+        "name": name,
+        "order": ctx.next_order.replace(ctx.next_order.get() + 1),
+        "route": route,
+        "source": path.display().to_string(),
+        "headings": headings,
+        "meta": document.meta.to_json(),
+        "outputs": files,
+    });
+    if !matches!(renderings[0], PandocOutput::ToBuffer(_)) {
+        node["kind"] = json!("binary");
+        node["mime"] = json!(ctx.formats[0].1);
+    }
+
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pandoc::PandocOption;
+
+    #[test]
+    fn child_routes_join_parent_and_slug() {
+        assert_eq!(child_route(&Some("/".to_string()), "about"), Some("/about".to_string()));
+        assert_eq!(child_route(&Some("/docs/".to_string()), "intro"), Some("/docs/intro".to_string()));
+        assert_eq!(child_route(&None, "about"), None);
+    }
+
+    #[test]
+    fn siblings_sharing_a_slug_get_numbered_routes() {
+        let file = |path: &str| TreeElement::File(PathBuf::from(path), SourceOptions::default());
+        let children = [
+            file("01-section.md"),
+            file("02-section.md"),
+            file("03-section-2.md"),
+            TreeElement::Nested(PathBuf::from("section"), Vec::new()),
+        ];
+
+        let routes: Vec<String> = child_routes(&Some("/docs".to_string()), &children).into_iter().flatten().collect();
+        assert_eq!(routes, ["/docs/section", "/docs/section-2", "/docs/section-2-2", "/docs/section-3"]);
+        assert_eq!(child_routes(&None, &children), [None, None, None, None]);
+    }
+
+    #[test]
+    fn routes_are_normalized() {
+        assert_eq!(normalize_route("/"), "/");
+        assert_eq!(normalize_route("/docs/"), "/docs");
+        assert_eq!(normalize_route("/docs"), "/docs");
+    }
+
+    #[test]
+    fn nodes_record_index_paths_and_crumbs() {
+        let tree = TreeElement::Nested(
+            PathBuf::from("content"),
+            vec![
+                TreeElement::File(PathBuf::from("content/index.md"), SourceOptions::default()),
+                TreeElement::Nested(
+                    PathBuf::from("content/02-guide"),
+                    vec![TreeElement::File(
+                        PathBuf::from("content/02-guide/01-setup.md"),
+                        SourceOptions::default(),
+                    )],
+                ),
+            ],
+        );
+
+        let mut nodes = Vec::new();
+        collect_nodes(&tree, &Some("/docs".to_string()), &mut vec![1], &mut Vec::new(), &mut nodes);
+
+        let routes: Vec<_> = nodes
+            .iter()
+            .map(|n| (n.route.as_deref().unwrap(), n.index.clone(), n.source.is_some()))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("/docs", vec![1], false),
+                ("/docs/index", vec![1, 0], true),
+                ("/docs/guide", vec![1, 1], false),
+                ("/docs/guide/setup", vec![1, 1, 0], true),
+            ]
+        );
+
+        let crumbs: Vec<_> = nodes[3].crumbs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(crumbs, vec!["content", "guide", "setup"]);
+    }
+
+    #[test]
+    fn anchors_are_checked_against_prefixed_ids() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/assets/mod_test");
+        let (about, index) = (root.join("about.md"), root.join("index.md"));
+        let prefixed = SourceOptions {
+            input_format: None,
+            pandoc_options: vec![PandocOption::IdPrefix("doc-".to_string())],
+        };
+        let tree = TreeElement::Nested(
+            root.clone(),
+            vec![
+                TreeElement::File(about.clone(), prefixed),
+                TreeElement::File(index.clone(), SourceOptions::default()),
+            ],
+        );
+        let mut nodes = Vec::new();
+        collect_nodes(&tree, &Some("/".to_string()), &mut vec![0], &mut Vec::new(), &mut nodes);
+
+        let page = |blocks: serde_json::Value| {
+            Document::from_json(&json!({ "meta": {}, "blocks": blocks }).to_string()).unwrap()
+        };
+        let link = |url: &str| json!({ "t": "Para", "c": [{ "t": "Link", "c": [["", [], []], [], [url, ""]] }] });
+        let options: Options =
+            syn::parse_str("mod_name = m, tree_name = T, content = [ compile_from_path(path: \"x\") ]").unwrap();
+        let resolve = |href: &str| {
+            let documents = HashMap::from([
+                (&about, page(json!([{ "t": "Header", "c": [1, ["team", [], []], []] }]))),
+                (&index, page(json!([link(href)]))),
+            ]);
+            resolve_links(&options, &nodes, &documents).map(|rewrites| rewrites[&index].clone())
+        };
+
+        assert_eq!(
+            resolve("about.md#doc-team").unwrap(),
+            [("about.md#doc-team".to_string(), "/about#doc-team".to_string())]
+        );
+        let err = resolve("about.md#team").err().unwrap();
+        assert!(err.to_string().ends_with("`about.md` has ids: doc-team"), "{}", err);
+    }
+}
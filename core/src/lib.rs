@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+};
+
+use ast::Document;
+use cache::Cache;
+use convert::{ConversionSettings, read_ast, render};
+use error::Errors;
+use generator::{export_content_tree, generate_content_tree};
+use options::{Element, Options};
+use pandoc::PandocOutput;
+use syn::parse::Parser;
+use threadpool::ThreadPool;
+use tqdm::tqdm;
+
+pub use backend::Backend;
+pub use builder::{Builder, Error, Generated};
+pub use check::LinkCheck;
+pub use export::{Export, MANIFEST_FILE};
+pub use pandoc::{InputFormat, MarkdownExtension, OutputFormat, PandocOption};
+pub use sidecar::SourceOptions;
+pub use tree::{FsTree, TreeElement};
+
+mod arg;
+mod assets;
+mod ast;
+mod backend;
+mod builder;
+mod cache;
+mod check;
+mod config;
+mod convert;
+mod discover;
+mod error;
+mod export;
+mod generator;
+mod links;
+mod mock;
+#[cfg(feature = "native")]
+mod native;
+mod options;
+mod paths;
+mod schema;
+mod search;
+mod sidecar;
+mod site;
+mod tree;
+
+const MOD_FILE_PREFIX: &str = "__mod__";
+
+/// Run the pipeline on the arguments of `pandoc_compile_html!`, for the
+/// proc macro crate
+#[doc(hidden)]
+pub fn expand_macro(input: proc_macro2::TokenStream) -> syn::Result<Generated> {
+    let options: Options = syn::parse2(input)?;
+    expand(&options)
+}
+
+/// Run the pipeline on the arguments of `pandoc_compile_html!`, rendering
+/// to files instead of a module. Relative paths resolve against `root`
+/// instead of the crate being compiled.
+pub fn export_macro(input: proc_macro2::TokenStream, root: &Path) -> syn::Result<Export> {
+    let options = (|input: syn::parse::ParseStream| Options::parse_in(input, root.to_path_buf())).parse2(input)?;
+    export(&options)
+}
+
+fn expand(options: &Options) -> syn::Result<Generated> {
+    let (tokens, trees) = convert(options, generate_content_tree)?;
+    Ok(Generated::new(tokens, trees, options.config.clone()))
+}
+
+fn export(options: &Options) -> syn::Result<Export> {
+    convert(options, export_content_tree).map(|(export, _)| export)
+}
+
+/// Convert every source, then pass the renderings and documents to `finish`
+fn convert<T>(
+    options: &Options,
+    finish: impl FnOnce(
+        &Options,
+        &[FsTree],
+        &HashMap<&PathBuf, Vec<PandocOutput>>,
+        &HashMap<&PathBuf, Document>,
+    ) -> syn::Result<T>,
+) -> syn::Result<(T, Vec<FsTree>)> {
+    let toolchain = options.backend.probe(options)?;
+
+    let pool = ThreadPool::new(options.nproc);
+    let mut errors = Errors::default();
+
+    let mut trees = Vec::new();
+    let mut srcs = Vec::new();
+    let mod_file_name = options.source_ext.as_ref()
+        .map_or(MOD_FILE_PREFIX.to_string(), |i| format!("{}.{}", MOD_FILE_PREFIX, i));
+    let source_options = options.source_options();
+
+    for element in options.content.iter() {
+        if let Element::CompileFromPath { path, route, span } = element {
+            let root = match paths::resolve(path, options.base.as_deref(), &options.root) {
+                Ok(root) => root,
+                Err(e) => {
+                    errors.push(syn::Error::new(*span, e));
+                    continue;
+                }
+            };
+
+            let asset_exts = options.assets.as_deref().unwrap_or_default();
+            let tree = FsTree::construct(
+                root,
+                route,
+                &mod_file_name,
+                &options.source_ext,
+                asset_exts,
+                &source_options,
+            );
+            match tree {
+                Ok(tree) => {
+                    srcs.extend(
+                        tree.sources()
+                            .into_iter()
+                            .map(|(src, src_options)| (src.clone(), *span, src_options.clone())),
+                    );
+                    trees.push(tree);
+                }
+                Err(e) => errors.push(syn::Error::new(
+                    *span,
+                    format!("Failed to explore {}: {}", path, e),
+                )),
+            }
+        }
+    }
+
+    let cache = if options.cache {
+        Cache::open(&toolchain.version, &options.root).map(Some).unwrap_or_else(|e| {
+            println!("Conversion cache unavailable: {}", e);
+            None
+        })
+    } else {
+        None
+    };
+
+    let mut settings = ConversionSettings::for_each_format(options);
+    for settings in settings.iter_mut() {
+        settings.pandoc_dir = toolchain.pandoc_dir.clone();
+    }
+    let mut pending = HashMap::new();
+    // Sources share a few distinct options, `probe` checked the macro's
+    let mut checked = HashMap::from([(format!("{:?}", source_options), Ok(()))]);
+
+    println!("Starting pandoc");
+    for (src, span, src_options) in tqdm(srcs.iter()) {
+        if pending.contains_key(src) {
+            // Already scheduled through another content element
+            continue;
+        }
+        let check = checked
+            .entry(format!("{:?}", src_options))
+            .or_insert_with(|| options.backend.check_source(&toolchain, src_options));
+        if let Err(e) = check {
+            errors.push(syn::Error::new(*span, format!("Cannot convert {}: {}", src.display(), e)));
+            continue;
+        }
+
+        // Options files may change the input format and options per directory
+        let settings: Vec<_> = settings.iter().map(|s| s.for_source(src_options)).collect();
+
+        // One job for the metadata AST and one per output format, all in parallel
+        let ast = {
+            let (src_file, settings) = (src.clone(), settings[0].clone());
+            schedule(&pool, cache.as_ref(), src, src_options, "ast", Cache::get_ast, move || {
+                read_ast(src_file, &settings)
+            })
+        };
+
+        let renders: Vec<_> = settings
+            .into_iter()
+            .map(|settings| {
+                let target = format!("{:?}", settings.output_format);
+                let src_file = src.clone();
+                schedule(&pool, cache.as_ref(), src, src_options, &target, Cache::get_output, move || {
+                    render(src_file, &settings)
+                })
+            })
+            .collect();
+
+        pending.insert(src, (ast, renders));
+    }
+
+    if cache.is_some() {
+        let (hits, total) = pending.values().fold((0, 0), |(hits, total), (ast, renders)| {
+            let jobs = std::iter::once(ast.is_cached()).chain(renders.iter().map(Pending::is_cached));
+            jobs.fold((hits, total), |(h, t), cached| (h + cached as usize, t + 1))
+        });
+        println!("Cache: {} hits, {} misses", hits, total - hits);
+    }
+
+    let mut outputs = HashMap::new();
+    let mut documents = HashMap::new();
+    println!("Gathering results");
+    for (path, span, _) in tqdm(srcs.iter()) {
+        let Some((ast, renders)) = pending.remove(path) else {
+            continue;
+        };
+
+        let ast = ast.wait(|key, ast| report_cache_failure(path, cache.as_ref().map(|c| c.put_ast(key, ast))));
+        let ast_error = match ast.and_then(|ast| Document::from_json(&ast)) {
+            Ok(document) => {
+                documents.insert(path, document);
+                None
+            }
+            Err(e) => {
+                errors.push(syn::Error::new(
+                    *span,
+                    format!("Failed to convert {}: {}", path.display(), e),
+                ));
+                Some(e)
+            }
+        };
+
+        let mut rendered = Vec::new();
+        for (render, settings) in renders.into_iter().zip(settings.iter()) {
+            let output = render.wait(|key, output| {
+                report_cache_failure(path, cache.as_ref().map(|c| c.put_output(key, output)))
+            });
+
+            match output {
+                Ok(output) => rendered.push(output),
+                // Most failures come from the reader and would repeat the AST error
+                Err(e) if ast_error.as_ref() == Some(&e) => {}
+                Err(e) => errors.push(syn::Error::new(
+                    *span,
+                    format!("Failed to convert {} to {}: {}", path.display(), settings.format_name(), e),
+                )),
+            }
+        }
+        outputs.insert(path, rendered);
+    }
+
+    errors.finish()?;
+    let result = finish(options, &trees, &outputs, &documents)?;
+    Ok((result, trees))
+}
+
+/// A conversion result, either taken from the cache or still being produced
+enum Pending<T> {
+    Cached(T),
+    Running(Option<String>, Receiver<Result<T, String>>),
+}
+
+impl<T> Pending<T> {
+    fn is_cached(&self) -> bool {
+        matches!(self, Pending::Cached(_))
+    }
+
+    /// Block until the result is available, passing fresh results with
+    /// their cache key to `store`
+    fn wait(self, store: impl FnOnce(&str, &T)) -> Result<T, String> {
+        match self {
+            Pending::Cached(value) => Ok(value),
+            Pending::Running(key, rx) => {
+                let result = rx
+                    .recv()
+                    .unwrap_or_else(|_| Err("the conversion thread panicked".to_string()));
+                if let (Ok(value), Some(key)) = (&result, key) {
+                    store(&key, value);
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Look `target` up in the cache, or run `job` on the pool
+fn schedule<T: Send + 'static>(
+    pool: &ThreadPool,
+    cache: Option<&Cache>,
+    src: &Path,
+    options: &SourceOptions,
+    target: &str,
+    lookup: impl Fn(&Cache, &str) -> Option<T>,
+    job: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Pending<T> {
+    let key = cache.and_then(|c| c.key(src, options, target).ok());
+    if let (Some(cache), Some(key)) = (cache, &key)
+        && let Some(value) = lookup(cache, key)
+    {
+        return Pending::Cached(value);
+    }
+
+    let (tx, rx) = channel();
+    pool.execute(move || {
+        let _ = tx.send(job());
+    });
+
+    Pending::Running(key, rx)
+}
+
+fn report_cache_failure(path: &Path, result: Option<std::io::Result<()>>) {
+    if let Some(Err(e)) = result {
+        println!("Failed to cache the result for {:?}: {}", path, e);
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A source file links can point at
+pub(crate) struct LinkTarget {
+    pub route: Option<String>,
+    /// Heading ids anchors are checked against, as emitted in the page
+    /// with its `IdPrefix`
+    pub ids: Vec<String>,
+}
+
+/// Maps links between source files to the routes of the generated pages
+pub(crate) struct LinkResolver {
+    /// Keyed by canonical path
+    targets: HashMap<PathBuf, LinkTarget>,
+    /// Extensions of the source files, links with them must hit a page
+    extensions: HashSet<String>,
+}
+
+/// Whether `target` starts with a URL scheme such as `https:`
+pub(crate) fn has_scheme(target: &str) -> bool {
+    match target.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        }
+        None => false,
+    }
+}
+
+impl LinkResolver {
+    pub(crate) fn new(targets: impl IntoIterator<Item = (PathBuf, LinkTarget)>) -> Self {
+        let mut resolver = Self {
+            targets: HashMap::new(),
+            extensions: HashSet::new(),
+        };
+
+        for (path, target) in targets {
+            if let Some(ext) = path.extension() {
+                resolver.extensions.insert(ext.to_string_lossy().into_owned());
+            }
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            resolver.targets.entry(path).or_insert(target);
+        }
+
+        resolver
+    }
+
+    /// New href for a link written in `src`, `None` if the link does not
+    /// point into the content trees and is kept as is
+    pub(crate) fn resolve(&self, src: &Path, link: &str) -> Result<Option<String>, String> {
+        if link.is_empty() || link.starts_with('#') || has_scheme(link) {
+            return Ok(None);
+        }
+
+        let (path, anchor) = match link.split_once('#') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (link, None),
+        };
+        let decoded = percent_decode(path);
+        let path = decoded.as_str();
+
+        // Relative to the linking file. Links rebased by pandoc's
+        // rebase_relative_paths extension are absolute, like the sources.
+        let resolved = fs::canonicalize(src.parent().unwrap_or(Path::new("")).join(path)).ok();
+
+        let Some(target) = resolved.as_ref().and_then(|path| self.targets.get(path)) else {
+            if path.starts_with('/') {
+                // A route, unless it is a source file rebased by pandoc
+                return Ok(None);
+            }
+            let is_page = Path::new(path)
+                .extension()
+                .is_some_and(|ext| self.extensions.contains(ext.to_string_lossy().as_ref()));
+            if is_page {
+                return Err(format!("link to `{}` does not point at a file in any content tree", link));
+            }
+            return Ok(None);
+        };
+
+        let Some(route) = &target.route else {
+            return Err(format!("link to `{}` points at a page without a route", link));
+        };
+
+        match anchor {
+            Some(anchor) if !target.ids.iter().any(|id| *id == percent_decode(anchor)) => Err(format!(
+                "link to `{}` has no matching heading, `{}` has ids: {}",
+                link,
+                path,
+                target.ids.join(", ")
+            )),
+            Some(anchor) => Ok(Some(format!("{}#{}", route, anchor))),
+            None => Ok(Some(route.clone())),
+        }
+    }
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as written
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+/// Replace `href` and `src` attribute values in rendered HTML
+pub(crate) fn rewrite_urls(html: &str, rewrites: &[(String, String)]) -> String {
+    let mut html = html.to_string();
+    for (from, to) in rewrites {
+        for attribute in ["href", "src"] {
+            html = html.replace(
+                &format!("{}=\"{}\"", attribute, escape_attribute(from)),
+                &format!("{}=\"{}\"", attribute, escape_attribute(to)),
+            );
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> (PathBuf, LinkResolver) {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/assets/mod_test");
+        let resolver = LinkResolver::new([
+            (
+                root.join("about.md"),
+                LinkTarget {
+                    route: Some("/about".to_string()),
+                    ids: vec!["team".to_string()],
+                },
+            ),
+            (
+                root.join("index.md"),
+                LinkTarget {
+                    route: None,
+                    ids: Vec::new(),
+                },
+            ),
+        ]);
+        (root, resolver)
+    }
+
+    #[test]
+    fn external_and_local_anchor_links_are_kept() {
+        let (root, resolver) = resolver();
+        let src = root.join("01-section/page1.md");
+        for link in ["https://example.com/a.md", "mailto:a@b.c", "#top", "/abs.md", "image.svg"] {
+            assert_eq!(resolver.resolve(&src, link), Ok(None), "{}", link);
+        }
+    }
+
+    #[test]
+    fn relative_links_become_routes() {
+        let (root, resolver) = resolver();
+        let src = root.join("01-section/page1.md");
+        assert_eq!(resolver.resolve(&src, "../about.md"), Ok(Some("/about".to_string())));
+        assert_eq!(resolver.resolve(&src, "../about.md#team"), Ok(Some("/about#team".to_string())));
+    }
+
+    #[test]
+    fn broken_links_are_errors() {
+        let (root, resolver) = resolver();
+        let src = root.join("01-section/page1.md");
+        assert!(resolver.resolve(&src, "missing.md").unwrap_err().contains("does not point at a file"));
+        assert!(resolver.resolve(&src, "../about.md#nope").unwrap_err().contains("no matching heading"));
+        assert!(resolver.resolve(&src, "../index.md").unwrap_err().contains("without a route"));
+    }
+
+    #[test]
+    fn links_do_not_resolve_against_the_working_directory() {
+        let (root, resolver) = resolver();
+        let src = root.join("01-section/page1.md");
+        // Tests run in core/, where this would name about.md
+        let link = "../tests/assets/mod_test/about.md";
+        assert!(Path::new(link).exists());
+        assert!(resolver.resolve(&src, link).unwrap_err().contains("does not point at a file"));
+
+        let absolute = root.join("about.md").display().to_string();
+        assert_eq!(resolver.resolve(&src, &absolute), Ok(Some("/about".to_string())));
+    }
+
+    #[test]
+    fn percent_encoded_links_are_decoded() {
+        let dir = std::env::temp_dir().join(format!("pandoc-generator-links-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("my page.md"), "# My page").unwrap();
+        let resolver = LinkResolver::new([(
+            dir.join("my page.md"),
+            LinkTarget {
+                route: Some("/my-page".to_string()),
+                ids: vec!["first%".to_string()],
+            },
+        )]);
+
+        let src = dir.join("index.md");
+        assert_eq!(resolver.resolve(&src, "my%20page.md"), Ok(Some("/my-page".to_string())));
+        assert_eq!(resolver.resolve(&src, "my%20page.md#first%25"), Ok(Some("/my-page#first%25".to_string())));
+        assert_eq!(percent_decode("100%"), "100%");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn urls_are_rewritten() {
+        let html = r#"<a href="a.md?x=1&amp;y">a</a> <a href="b.md">b</a> <img src="a.md?x=1&amp;y" />"#;
+        let rewrites = [("a.md?x=1&y".to_string(), "/a".to_string())];
+        assert_eq!(
+            rewrite_urls(html, &rewrites),
+            r#"<a href="/a">a</a> <a href="b.md">b</a> <img src="/a" />"#
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::Path};
+
+use pandoc::PandocOutput;
+use serde_json::{Map, Value, json};
+
+use crate::convert::{ConversionSettings, is_binary};
+
+/// Identifies mock conversions in the cache
+pub(crate) const VERSION: &str = "mock backend 2";
+
+/// Sources containing this fail to convert, to exercise error reporting
+const FAIL_MARKER: &str = "MOCK_FAIL";
+
+/// Split a leading `---` delimited front matter block off `text`
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let Some(rest) = text.strip_prefix("---\n") else {
+        return (None, text);
+    };
+
+    match rest.find("\n---\n") {
+        Some(end) => (Some(&rest[..end]), &rest[end + 5..]),
+        None => match rest.strip_suffix("\n---") {
+            Some(front) => (Some(front), ""),
+            None => (None, text),
+        },
+    }
+}
+
+fn read(src: &Path) -> Result<String, String> {
+    let text = fs::read_to_string(src).map_err(|e| format!("failed to read {}: {}", src.display(), e))?;
+    if text.contains(FAIL_MARKER) {
+        return Err(format!("mock backend refuses to convert {}", src.display()));
+    }
+    Ok(text)
+}
+
+/// Output format, then the input format and options when set, as pandoc
+/// would be asked for them
+fn describe(settings: &ConversionSettings) -> String {
+    let mut out = settings.format_name();
+    if let Some((fmt, exts)) = &settings.input_format {
+        out.push_str(&format!(" from {}", fmt));
+        for ext in exts {
+            out.push_str(&format!("+{}", ext));
+        }
+    }
+    if !settings.pandoc_options.is_empty() {
+        let options: Vec<String> = settings.pandoc_options.iter().map(|o| format!("{:?}", o)).collect();
+        out.push_str(&format!(" with {}", options.join(", ")));
+    }
+    out
+}
+
+/// The source body, front matter removed, wrapped in markers naming the
+/// output format and the settings it was converted with. Binary formats
+/// get the same text as bytes.
+pub(crate) fn render(src: &Path, settings: &ConversionSettings) -> Result<PandocOutput, String> {
+    let text = read(src)?;
+    let (_, body) = split_front_matter(&text);
+    let format = describe(settings);
+
+    match &settings.output_format {
+        Some((fmt, _)) if is_binary(fmt) => Ok(PandocOutput::ToBufferRaw(format!("MOCK {}\n{}", format, body).into_bytes())),
+        _ => Ok(PandocOutput::ToBuffer(format!("<!-- mock {} -->\n{}<!-- /mock -->\n", format, body))),
+    }
+}
+
+fn words(text: &str) -> Vec<Value> {
+    let mut out = Vec::new();
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            out.push(json!({ "t": "Space" }));
+        }
+        out.push(json!({ "t": "Str", "c": word }));
+    }
+    out
+}
+
+fn slug(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A pandoc JSON AST: flat `key: value` front matter, `#` headings and
+/// paragraphs
+pub(crate) fn read_ast(src: &Path, _: &ConversionSettings) -> Result<String, String> {
+    let text = read(src)?;
+    let (front_matter, body) = split_front_matter(&text);
+
+    let meta: Map<String, Value> = front_matter
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let value = match value.trim() {
+                "true" => json!({ "t": "MetaBool", "c": true }),
+                "false" => json!({ "t": "MetaBool", "c": false }),
+                value => json!({ "t": "MetaInlines", "c": words(value) }),
+            };
+            (key.trim().to_string(), value)
+        })
+        .collect();
+
+    let blocks: Vec<Value> = body
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
+        .map(|block| {
+            let level = block.chars().take_while(|c| *c == '#').count();
+            match block[level..].strip_prefix(' ') {
+                Some(title) if (1..=6).contains(&level) => {
+                    json!({ "t": "Header", "c": [level, [slug(title), [], []], words(title)] })
+                }
+                _ => json!({ "t": "Para", "c": words(block) }),
+            }
+        })
+        .collect();
+
+    Ok(json!({ "pandoc-api-version": [1, 23, 1], "meta": meta, "blocks": blocks }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{Document, Heading, MetaValue},
+        backend::Backend,
+    };
+    use pandoc::{InputFormat, MarkdownExtension, OutputFormat, PandocOption};
+
+    fn settings(output: Option<OutputFormat>) -> ConversionSettings {
+        ConversionSettings {
+            input_format: None,
+            output_format: output.map(|fmt| (fmt, Vec::new())),
+            pandoc_options: Vec::new(),
+            backend: Backend::Mock,
+            pandoc_dir: None,
+        }
+    }
+
+    fn write(name: &str, text: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("pandoc-generator-mock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn front_matter_is_split_off() {
+        assert_eq!(split_front_matter("---\na: b\n---\nbody"), (Some("a: b"), "body"));
+        assert_eq!(split_front_matter("---\na: b\n---"), (Some("a: b"), ""));
+        assert_eq!(split_front_matter("# no front matter"), (None, "# no front matter"));
+    }
+
+    #[test]
+    fn output_wraps_the_body() {
+        let src = write("page.md", "---\ntitle: T\n---\n# Hi\n");
+
+        let Ok(PandocOutput::ToBuffer(html)) = render(&src, &settings(None)) else {
+            panic!("expected text output");
+        };
+        assert_eq!(html, "<!-- mock html -->\n# Hi\n<!-- /mock -->\n");
+
+        let Ok(PandocOutput::ToBufferRaw(bytes)) = render(&src, &settings(Some(OutputFormat::Docx))) else {
+            panic!("expected binary output");
+        };
+        assert_eq!(bytes, b"MOCK docx\n# Hi\n");
+    }
+
+    #[test]
+    fn output_names_the_input_format_and_options() {
+        let src = write("options.md", "# Hi\n");
+        let settings = ConversionSettings {
+            input_format: Some((InputFormat::Commonmark, vec![MarkdownExtension::Footnotes])),
+            pandoc_options: vec![PandocOption::NumberSections, PandocOption::IdPrefix("a-".to_string())],
+            ..settings(Some(OutputFormat::Html5))
+        };
+
+        let Ok(PandocOutput::ToBuffer(html)) = render(&src, &settings) else {
+            panic!("expected text output");
+        };
+        assert_eq!(
+            html,
+            "<!-- mock html5 from commonmark+footnotes with NumberSections, IdPrefix(\"a-\") -->\n# Hi\n<!-- /mock -->\n"
+        );
+    }
+
+    #[test]
+    fn ast_has_meta_headings_and_text() {
+        let src = write("ast.md", "---\ntitle: Hello World\ndraft: true\n---\n# Getting Started!\n\nSome  text\nhere.\n");
+        let doc = Document::from_json(&read_ast(&src, &settings(None)).unwrap()).unwrap();
+
+        assert_eq!(
+            doc.meta,
+            MetaValue::Map(vec![
+                ("draft".to_string(), MetaValue::Bool(true)),
+                ("title".to_string(), MetaValue::String("Hello World".to_string())),
+            ])
+        );
+        assert_eq!(
+            doc.headings,
+            vec![Heading { level: 1, id: "getting-started".to_string(), text: "Getting Started!".to_string() }]
+        );
+        assert_eq!(doc.text, "Getting Started!\n\nSome text here.");
+    }
+
+    #[test]
+    fn marked_sources_fail() {
+        let src = write("fail.md", "MOCK_FAIL\n");
+        assert!(matches!(render(&src, &settings(None)), Err(e) if e.contains("refuses to convert")));
+        assert!(read_ast(&src, &settings(None)).is_err());
+    }
+}
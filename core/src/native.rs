@@ -0,0 +1,487 @@
+use std::{fs, path::Path};
+
+use pandoc::{InputFormat, MarkdownExtension, OutputFormat, PandocOutput};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options as ParserOptions, Parser, Tag, html};
+use serde_json::{Map, Value, json};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{
+    ast::stringify_blocks, convert::ConversionSettings, links::has_scheme, options::Options, sidecar::SourceOptions,
+};
+
+/// Identifies native conversions in the cache
+pub(crate) const VERSION: &str = "native backend (pulldown-cmark 0.13)";
+
+/// Reject settings the native backend cannot honour, before converting
+pub(crate) fn check(options: &Options) -> Result<(), String> {
+    for (fmt, _) in &options.output_format {
+        if !matches!(fmt, OutputFormat::Html | OutputFormat::Html5 | OutputFormat::Plain) {
+            return Err(format!(
+                "output format `{}` is not supported by the native backend, use html, html5 or plain",
+                fmt
+            ));
+        }
+    }
+
+    check_source(&options.source_options())
+}
+
+/// Reject an input format or pandoc options the native backend cannot honour
+pub(crate) fn check_source(options: &SourceOptions) -> Result<(), String> {
+    let (input, exts) = options
+        .input_format
+        .clone()
+        .unwrap_or((InputFormat::Markdown, Vec::new()));
+    parser_options(&input, &exts)?;
+
+    match options.pandoc_options.first() {
+        Some(opt) => Err(format!("pandoc option {:?} is not supported by the native backend", opt)),
+        None => Ok(()),
+    }
+}
+
+/// Parser options for an input format and its extensions, and whether
+/// relative links are rebased onto the source directory
+fn parser_options(input: &InputFormat, exts: &[MarkdownExtension]) -> Result<(ParserOptions, bool), String> {
+    let pandoc_markdown = ParserOptions::ENABLE_TABLES
+        | ParserOptions::ENABLE_FOOTNOTES
+        | ParserOptions::ENABLE_STRIKETHROUGH
+        | ParserOptions::ENABLE_TASKLISTS
+        | ParserOptions::ENABLE_SMART_PUNCTUATION
+        | ParserOptions::ENABLE_HEADING_ATTRIBUTES
+        | ParserOptions::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | ParserOptions::ENABLE_MATH
+        | ParserOptions::ENABLE_DEFINITION_LIST
+        | ParserOptions::ENABLE_SUPERSCRIPT
+        | ParserOptions::ENABLE_SUBSCRIPT;
+    let gfm = ParserOptions::ENABLE_TABLES
+        | ParserOptions::ENABLE_FOOTNOTES
+        | ParserOptions::ENABLE_STRIKETHROUGH
+        | ParserOptions::ENABLE_TASKLISTS
+        | ParserOptions::ENABLE_GFM;
+
+    let mut options = match input {
+        InputFormat::Markdown | InputFormat::CommonmarkX => pandoc_markdown,
+        InputFormat::MarkdownGithub => gfm,
+        InputFormat::Other(name) if name == "gfm" => gfm,
+        InputFormat::Commonmark | InputFormat::MarkdownStrict => ParserOptions::empty(),
+        other => {
+            return Err(format!(
+                "input format `{}` is not supported by the native backend, use markdown, commonmark, commonmark_x or gfm",
+                other
+            ));
+        }
+    };
+
+    let mut rebase = false;
+    for ext in exts {
+        options |= match ext {
+            MarkdownExtension::Smart => ParserOptions::ENABLE_SMART_PUNCTUATION,
+            MarkdownExtension::Attributes | MarkdownExtension::HeaderAttributes => {
+                ParserOptions::ENABLE_HEADING_ATTRIBUTES
+            }
+            MarkdownExtension::PipeTables => ParserOptions::ENABLE_TABLES,
+            MarkdownExtension::Footnotes => ParserOptions::ENABLE_FOOTNOTES,
+            MarkdownExtension::Strikeout => ParserOptions::ENABLE_STRIKETHROUGH,
+            MarkdownExtension::Superscript => ParserOptions::ENABLE_SUPERSCRIPT,
+            MarkdownExtension::Subscript => ParserOptions::ENABLE_SUBSCRIPT,
+            MarkdownExtension::TaskLists => ParserOptions::ENABLE_TASKLISTS,
+            MarkdownExtension::DefinitionLists => ParserOptions::ENABLE_DEFINITION_LIST,
+            MarkdownExtension::TexMathDollars => ParserOptions::ENABLE_MATH,
+            MarkdownExtension::YamlMetadataBlock => ParserOptions::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+            // Always on in CommonMark
+            MarkdownExtension::AutoIdentifiers
+            | MarkdownExtension::FencedCodeBlocks
+            | MarkdownExtension::BacktickCodeBlocks
+            | MarkdownExtension::RawHtml
+            | MarkdownExtension::ShortcutReferenceLinks
+            | MarkdownExtension::IntrawordUnderscores
+            | MarkdownExtension::AllSymbolsEscapable => ParserOptions::empty(),
+            MarkdownExtension::RebaseRelativePaths => {
+                rebase = true;
+                ParserOptions::empty()
+            }
+            other => return Err(format!("extension `{}` is not supported by the native backend", other)),
+        };
+    }
+
+    Ok((options, rebase))
+}
+
+/// Heading id the way pandoc's auto_identifiers builds it
+fn auto_identifier(text: &str) -> String {
+    let id: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ' | '\n'))
+        .map(|c| if c.is_whitespace() { '-' } else { c })
+        .flat_map(char::to_lowercase)
+        .skip_while(|c| !c.is_alphabetic())
+        .collect();
+
+    if id.is_empty() { "section".to_string() } else { id }
+}
+
+/// A parsed source: its events, with heading ids filled in, and the YAML
+/// front matter
+struct Source {
+    events: Vec<Event<'static>>,
+    front_matter: String,
+}
+
+fn parse(src: &Path, settings: &ConversionSettings) -> Result<Source, String> {
+    let text = fs::read_to_string(src).map_err(|e| format!("failed to read {}: {}", src.display(), e))?;
+    let (input, exts) = settings
+        .input_format
+        .clone()
+        .unwrap_or((InputFormat::Markdown, Vec::new()));
+    let (options, rebase) = parser_options(&input, &exts)?;
+
+    let mut events: Vec<Event<'static>> = Parser::new_ext(&text, options).map(Event::into_static).collect();
+    let mut front_matter = String::new();
+    let mut used_ids = Vec::new();
+
+    for i in 0..events.len() {
+        match &events[i] {
+            Event::Start(Tag::MetadataBlock(_)) => {
+                if let Some(Event::Text(yaml)) = events.get(i + 1) {
+                    front_matter.push_str(yaml);
+                }
+            }
+            Event::Start(Tag::Heading { id, .. }) => {
+                let id = match id {
+                    Some(id) => id.to_string(),
+                    None => {
+                        let text = events[i + 1..]
+                            .iter()
+                            .take_while(|e| !matches!(e, Event::End(pulldown_cmark::TagEnd::Heading(_))))
+                            .filter_map(|e| match e {
+                                Event::Text(t) | Event::Code(t) | Event::InlineMath(t) => Some(t.as_ref()),
+                                Event::SoftBreak => Some(" "),
+                                _ => None,
+                            })
+                            .collect::<String>();
+                        let base = auto_identifier(&text);
+                        let mut id = base.clone();
+                        let mut n = 0;
+                        while used_ids.contains(&id) {
+                            n += 1;
+                            id = format!("{}-{}", base, n);
+                        }
+                        id
+                    }
+                };
+                used_ids.push(id.clone());
+                if let Event::Start(Tag::Heading { id: slot, .. }) = &mut events[i] {
+                    *slot = Some(CowStr::from(id));
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. })
+                if rebase && !dest_url.is_empty() && !dest_url.starts_with(['#', '/']) && !has_scheme(dest_url) =>
+            {
+                let rebased = src.parent().unwrap_or(Path::new("")).join(dest_url.as_ref());
+                if let Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) = &mut events[i] {
+                    *dest_url = CowStr::from(rebased.display().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Source { events, front_matter })
+}
+
+/// Render one source file to HTML or plain text
+pub(crate) fn render(src: &Path, settings: &ConversionSettings) -> Result<PandocOutput, String> {
+    let source = parse(src, settings)?;
+
+    match settings.output_format.as_ref().map(|(fmt, _)| fmt) {
+        Some(OutputFormat::Plain) => {
+            let blocks = to_blocks(&source.events);
+            Ok(PandocOutput::ToBuffer(format!("{}\n", stringify_blocks(&blocks))))
+        }
+        _ => {
+            let mut out = String::new();
+            html::push_html(&mut out, source.events.into_iter());
+            Ok(PandocOutput::ToBuffer(out))
+        }
+    }
+}
+
+/// Read one source file into a pandoc-compatible JSON AST
+pub(crate) fn read_ast(src: &Path, settings: &ConversionSettings) -> Result<String, String> {
+    let source = parse(src, settings)?;
+
+    let meta = if source.front_matter.trim().is_empty() {
+        Map::new()
+    } else {
+        let docs = YamlLoader::load_from_str(&source.front_matter)
+            .map_err(|e| format!("invalid YAML front matter in {}: {}", src.display(), e))?;
+        match docs.into_iter().next().map(|doc| meta_value(&doc)) {
+            Some(Value::Object(map)) if map.get("t") == Some(&json!("MetaMap")) => match &map["c"] {
+                Value::Object(entries) => entries.clone(),
+                _ => Map::new(),
+            },
+            _ => return Err(format!("front matter of {} is not a mapping", src.display())),
+        }
+    };
+
+    Ok(json!({
+        "pandoc-api-version": [1, 23, 1],
+        "meta": meta,
+        "blocks": to_blocks(&source.events)
+    })
+    .to_string())
+}
+
+fn meta_value(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::Hash(map) => {
+            let entries: Map<String, Value> = map
+                .iter()
+                .filter_map(|(key, value)| {
+                    let key = match key {
+                        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+                        Yaml::Integer(n) => n.to_string(),
+                        Yaml::Boolean(b) => b.to_string(),
+                        _ => return None,
+                    };
+                    Some((key, meta_value(value)))
+                })
+                .collect();
+            json!({ "t": "MetaMap", "c": entries })
+        }
+        Yaml::Array(items) => json!({ "t": "MetaList", "c": items.iter().map(meta_value).collect::<Vec<_>>() }),
+        Yaml::Boolean(b) => json!({ "t": "MetaBool", "c": b }),
+        Yaml::String(s) | Yaml::Real(s) => json!({ "t": "MetaInlines", "c": words(s) }),
+        Yaml::Integer(n) => json!({ "t": "MetaInlines", "c": words(&n.to_string()) }),
+        _ => json!({ "t": "MetaString", "c": "" }),
+    }
+}
+
+/// Split text into pandoc `Str` and `Space` inlines
+fn words(text: &str) -> Vec<Value> {
+    let mut out = Vec::new();
+    let mut word = String::new();
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !word.is_empty() {
+                out.push(json!({ "t": "Str", "c": std::mem::take(&mut word) }));
+            }
+            if out.last() != Some(&json!({ "t": "Space" })) {
+                out.push(json!({ "t": "Space" }));
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        out.push(json!({ "t": "Str", "c": word }));
+    }
+
+    out
+}
+
+fn is_block(value: &Value) -> bool {
+    matches!(
+        value.get("t").and_then(Value::as_str),
+        Some(
+            "Para" | "Plain" | "Header" | "BlockQuote" | "CodeBlock" | "RawBlock" | "BulletList" | "OrderedList"
+                | "Div" | "HorizontalRule"
+        )
+    )
+}
+
+/// Group loose inlines, as found in tight list items and table cells, into
+/// `Plain` blocks
+fn blocks(children: Vec<Value>) -> Vec<Value> {
+    let mut out = Vec::new();
+    let mut inlines = Vec::new();
+
+    for child in children {
+        if is_block(&child) {
+            if !inlines.is_empty() {
+                out.push(json!({ "t": "Plain", "c": std::mem::take(&mut inlines) }));
+            }
+            out.push(child);
+        } else {
+            inlines.push(child);
+        }
+    }
+    if !inlines.is_empty() {
+        out.push(json!({ "t": "Plain", "c": inlines }));
+    }
+
+    out
+}
+
+fn raw_text(children: &[Value]) -> String {
+    children.iter().filter_map(Value::as_str).collect()
+}
+
+/// Pandoc JSON blocks of a parsed document
+fn to_blocks(events: &[Event<'static>]) -> Value {
+    const NO_ATTR: fn() -> Value = || json!(["", [], []]);
+    let mut stack: Vec<(Option<&Tag<'static>>, Vec<Value>)> = vec![(None, Vec::new())];
+
+    for event in events {
+        let top = stack.last_mut().unwrap();
+        match event {
+            Event::Start(tag) => stack.push((Some(tag), Vec::new())),
+            Event::End(_) => {
+                let (tag, children) = stack.pop().unwrap();
+                let node = match tag.unwrap() {
+                    Tag::Paragraph => json!({ "t": "Para", "c": children }),
+                    Tag::Heading { level, id, classes, .. } => json!({
+                        "t": "Header",
+                        "c": [*level as u8, [id.as_deref().unwrap_or_default(), classes.iter().map(|c| c.as_ref()).collect::<Vec<_>>(), []], children]
+                    }),
+                    Tag::BlockQuote(_) => json!({ "t": "BlockQuote", "c": blocks(children) }),
+                    Tag::CodeBlock(kind) => {
+                        let classes = match kind {
+                            CodeBlockKind::Fenced(lang) if !lang.is_empty() => vec![lang.to_string()],
+                            _ => Vec::new(),
+                        };
+                        json!({ "t": "CodeBlock", "c": [["", classes, []], raw_text(&children)] })
+                    }
+                    Tag::HtmlBlock => json!({ "t": "RawBlock", "c": ["html", raw_text(&children)] }),
+                    Tag::List(None) => json!({ "t": "BulletList", "c": children }),
+                    Tag::List(Some(start)) => json!({
+                        "t": "OrderedList",
+                        "c": [[start, { "t": "Decimal" }, { "t": "Period" }], children]
+                    }),
+                    Tag::Item => Value::Array(blocks(children)),
+                    Tag::Emphasis => json!({ "t": "Emph", "c": children }),
+                    Tag::Strong => json!({ "t": "Strong", "c": children }),
+                    Tag::Strikethrough => json!({ "t": "Strikeout", "c": children }),
+                    Tag::Superscript => json!({ "t": "Superscript", "c": children }),
+                    Tag::Subscript => json!({ "t": "Subscript", "c": children }),
+                    Tag::Link { dest_url, title, .. } => {
+                        json!({ "t": "Link", "c": [NO_ATTR(), children, [dest_url.as_ref(), title.as_ref()]] })
+                    }
+                    Tag::Image { dest_url, title, .. } => {
+                        json!({ "t": "Image", "c": [NO_ATTR(), children, [dest_url.as_ref(), title.as_ref()]] })
+                    }
+                    Tag::MetadataBlock(_) => continue,
+                    // Tables, footnotes and definition lists keep their text
+                    _ => json!({ "t": "Div", "c": [NO_ATTR(), blocks(children)] }),
+                };
+                stack.last_mut().unwrap().1.push(node);
+            }
+            Event::Text(text) | Event::Html(text) => match top.0 {
+                Some(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => top.1.push(json!(text.as_ref())),
+                _ if matches!(event, Event::Html(_)) => top.1.push(json!({ "t": "RawInline", "c": ["html", text.as_ref()] })),
+                _ => top.1.extend(words(text)),
+            },
+            Event::Code(code) => top.1.push(json!({ "t": "Code", "c": [NO_ATTR(), code.as_ref()] })),
+            Event::InlineMath(math) => top.1.push(json!({ "t": "Math", "c": [{ "t": "InlineMath" }, math.as_ref()] })),
+            Event::DisplayMath(math) => top.1.push(json!({ "t": "Math", "c": [{ "t": "DisplayMath" }, math.as_ref()] })),
+            Event::InlineHtml(html) => top.1.push(json!({ "t": "RawInline", "c": ["html", html.as_ref()] })),
+            Event::SoftBreak => top.1.push(json!({ "t": "SoftBreak" })),
+            Event::HardBreak => top.1.push(json!({ "t": "LineBreak" })),
+            Event::Rule => top.1.push(json!({ "t": "HorizontalRule" })),
+            Event::TaskListMarker(done) => top.1.push(json!({ "t": "Str", "c": if *done { "☒" } else { "☐" } })),
+            Event::FootnoteReference(_) => {}
+        }
+    }
+
+    Value::Array(blocks(stack.pop().unwrap().1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Document, Heading, MetaValue};
+
+    fn settings(input: Option<(InputFormat, Vec<MarkdownExtension>)>, output: Option<OutputFormat>) -> ConversionSettings {
+        ConversionSettings {
+            input_format: input,
+            output_format: output.map(|fmt| (fmt, Vec::new())),
+            pandoc_options: Vec::new(),
+            backend: crate::backend::Backend::Native,
+            pandoc_dir: None,
+        }
+    }
+
+    fn write(name: &str, text: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("pandoc-generator-native-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn identifiers_follow_pandoc() {
+        assert_eq!(auto_identifier("Getting Started"), "getting-started");
+        assert_eq!(auto_identifier("1. Intro: *the* API!"), "intro-the-api");
+        assert_eq!(auto_identifier("v1.2_beta"), "v1.2_beta");
+        assert_eq!(auto_identifier("123"), "section");
+    }
+
+    #[test]
+    fn unsupported_extensions_are_errors() {
+        assert!(parser_options(&InputFormat::Markdown, &[MarkdownExtension::Smart]).is_ok());
+        let err = parser_options(&InputFormat::Markdown, &[MarkdownExtension::GridTables]).unwrap_err();
+        assert_eq!(err, "extension `grid_tables` is not supported by the native backend");
+        assert!(parser_options(&InputFormat::Rst, &[]).unwrap_err().contains("input format `rst`"));
+    }
+
+    #[test]
+    fn html_has_heading_ids() {
+        let src = write("ids.md", "# Intro\n\n## Intro\n\n## Custom {#mine}\n\nText with [a link](other.md).\n");
+        let Ok(PandocOutput::ToBuffer(html)) = render(&src, &settings(None, None)) else {
+            panic!("expected text output");
+        };
+
+        assert!(html.contains(r#"<h1 id="intro">Intro</h1>"#));
+        assert!(html.contains(r#"<h2 id="intro-1">Intro</h2>"#));
+        assert!(html.contains(r#"<h2 id="mine">Custom</h2>"#));
+        assert!(html.contains(r#"<a href="other.md">a link</a>"#));
+    }
+
+    #[test]
+    fn ast_has_meta_headings_and_links() {
+        let src = write(
+            "ast.md",
+            "---\ntitle: Hello World\ndraft: false\ntags:\n  - rust\nweight: 3\n---\n\n# Post\n\n- item [x](a.md)\n- `code`\n",
+        );
+        let doc = Document::from_json(&read_ast(&src, &settings(None, None)).unwrap()).unwrap();
+
+        assert_eq!(
+            doc.meta,
+            MetaValue::Map(vec![
+                ("draft".to_string(), MetaValue::Bool(false)),
+                ("tags".to_string(), MetaValue::List(vec![MetaValue::String("rust".to_string())])),
+                ("title".to_string(), MetaValue::String("Hello World".to_string())),
+                ("weight".to_string(), MetaValue::String("3".to_string())),
+            ])
+        );
+        assert_eq!(doc.headings, vec![Heading { level: 1, id: "post".to_string(), text: "Post".to_string() }]);
+        assert_eq!(doc.links, vec!["a.md"]);
+        assert_eq!(doc.text, "Post\n\nitem x\ncode");
+    }
+
+    #[test]
+    fn relative_links_can_be_rebased() {
+        let src = write("rebase.md", "![img](image.svg) [abs](/x) [web](https://x.org)\n");
+        let input = Some((InputFormat::Markdown, vec![MarkdownExtension::RebaseRelativePaths]));
+        let Ok(PandocOutput::ToBuffer(html)) = render(&src, &settings(input, None)) else {
+            panic!("expected text output");
+        };
+
+        let image = src.parent().unwrap().join("image.svg");
+        assert!(html.contains(&format!(r#"src="{}""#, image.display())));
+        assert!(html.contains(r#"href="/x""#));
+        assert!(html.contains(r#"href="https://x.org""#));
+    }
+
+    #[test]
+    fn plain_output_is_text() {
+        let src = write("plain.md", "# Title\n\nSome *text*.\n");
+        let Ok(PandocOutput::ToBuffer(text)) = render(&src, &settings(None, Some(OutputFormat::Plain))) else {
+            panic!("expected text output");
+        };
+        assert_eq!(text, "Title\n\nSome text.\n");
+    }
+}
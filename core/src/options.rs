@@ -4,10 +4,19 @@ use syn::{
     ExprLit, Ident, Lit, Token, bracketed, parenthesized, parse::Parse, punctuated::Punctuated,
 };
 
+use std::path::PathBuf;
+
 use crate::arg::parse_pandoc_options;
+use crate::backend::Backend;
+use crate::config::ConfigFile;
+use crate::discover::VersionReq;
+use crate::schema::MetadataSchema;
+use crate::sidecar::SourceOptions;
+use crate::check::LinkCheck;
+use crate::site::FeedOptions;
 
 /// Parse markdown extensions from a bracketed list
-fn parse_markdown_extensions(input: &syn::parse::ParseStream) -> syn::Result<Vec<MarkdownExtension>> {
+pub(crate) fn parse_markdown_extensions(input: &syn::parse::ParseStream) -> syn::Result<Vec<MarkdownExtension>> {
     let mut exts = Vec::new();
     if input.peek(syn::token::Bracket) {
         let ext_stream;
@@ -83,7 +92,7 @@ fn parse_markdown_extensions(input: &syn::parse::ParseStream) -> syn::Result<Vec
 }
 
 /// Parse an input format with optional extensions
-fn parse_input_format(input: syn::parse::ParseStream) -> syn::Result<(InputFormat, Vec<MarkdownExtension>)> {
+pub(crate) fn parse_input_format(input: syn::parse::ParseStream) -> syn::Result<(InputFormat, Vec<MarkdownExtension>)> {
     let fmt_ident: Ident = input.parse()?;
     let base = fmt_ident.to_string().to_lowercase();
 
@@ -150,6 +159,8 @@ fn parse_output_format(input: syn::parse::ParseStream) -> syn::Result<(OutputFor
         "open_document" | "opendocument" => OutputFormat::OpenDocument,
         "odt" => OutputFormat::Odt,
         "docx" => OutputFormat::Docx,
+        "epub" => OutputFormat::Epub,
+        "epub3" => OutputFormat::Epub3,
         other => OutputFormat::Other(other.to_string()),
     };
 
@@ -157,6 +168,34 @@ fn parse_output_format(input: syn::parse::ParseStream) -> syn::Result<(OutputFor
     Ok((fmt, exts))
 }
 
+/// Parse one output format, or a bracketed list of distinct formats
+pub(crate) fn parse_output_formats(input: syn::parse::ParseStream) -> syn::Result<Vec<(OutputFormat, Vec<MarkdownExtension>)>> {
+    if !input.peek(syn::token::Bracket) {
+        return Ok(vec![parse_output_format(input)?]);
+    }
+
+    let list;
+    let bracket = bracketed!(list in input);
+    let formats = Punctuated::<_, Token![,]>::parse_terminated_with(&list, parse_output_format)?;
+
+    if formats.is_empty() {
+        return Err(syn::Error::new(bracket.span.join(), "output_format list is empty"));
+    }
+
+    let mut out: Vec<(OutputFormat, Vec<MarkdownExtension>)> = Vec::new();
+    for (fmt, exts) in formats {
+        if out.iter().any(|(f, _)| f.to_string() == fmt.to_string()) {
+            return Err(syn::Error::new(
+                bracket.span.join(),
+                format!("output format `{}` listed multiple times", fmt),
+            ));
+        }
+        out.push((fmt, exts));
+    }
+
+    Ok(out)
+}
+
 mod keywords {
     use syn::custom_keyword;
 
@@ -174,10 +213,30 @@ mod keywords {
     custom_keyword!(source_ext);
     custom_keyword!(input_format);
     custom_keyword!(output_format);
+    custom_keyword!(metadata);
+    custom_keyword!(cache);
+    custom_keyword!(base);
+    custom_keyword!(toc_depth);
+    custom_keyword!(search_index);
+    custom_keyword!(search_json);
+    custom_keyword!(base_url);
+    custom_keyword!(sitemap);
+    custom_keyword!(feed);
+    custom_keyword!(check_links);
+    custom_keyword!(assets);
+    custom_keyword!(inline_assets_below);
+    custom_keyword!(backend);
+    custom_keyword!(pandoc_path);
+    custom_keyword!(pandoc_version);
+    custom_keyword!(config);
 }
 
 pub(crate) enum Element {
-    CompileFromPath { path: String, route: Option<String> },
+    CompileFromPath {
+        path: String,
+        route: Option<String>,
+        span: Span,
+    },
 
     Special { ty: String },
 }
@@ -212,7 +271,7 @@ impl Parse for Element {
 
             Ok(Element::Special { ty })
         } else if lookahead.peek(keywords::compile_from_path) {
-            let _ = input.parse::<keywords::compile_from_path>()?;
+            let keyword = input.parse::<keywords::compile_from_path>()?;
             let arg;
             parenthesized!(arg in input);
 
@@ -220,7 +279,7 @@ impl Parse for Element {
             let _ = arg.parse::<Token![:]>()?;
             let path = parse_string_arg(&arg)?;
 
-            let route = if let Ok(_) = arg.parse::<Token![,]>() {
+            let route = if arg.parse::<Token![,]>().is_ok() {
                 let _ = arg.parse::<keywords::route>()?;
                 let _ = arg.parse::<Token![:]>()?;
                 Some(parse_string_arg(&arg)?)
@@ -228,7 +287,11 @@ impl Parse for Element {
                 None
             };
 
-            Ok(Element::CompileFromPath { path, route })
+            Ok(Element::CompileFromPath {
+                path,
+                route,
+                span: keyword.span,
+            })
         } else {
             Err(lookahead.error())
         }
@@ -241,22 +304,111 @@ pub(crate) struct Options {
     pub content: Punctuated<Element, Token![,]>,
     pub source_ext: Option<String>,
     pub input_format: Option<(InputFormat, Vec<MarkdownExtension>)>,
-    pub output_format: Option<(OutputFormat, Vec<MarkdownExtension>)>,
+    /// Every requested output format, the first one being the primary
+    pub output_format: Vec<(OutputFormat, Vec<MarkdownExtension>)>,
     pub pandoc_options: Vec<PandocOption>,
     pub nproc: usize,
+    pub metadata: Option<MetadataSchema>,
+    pub cache: bool,
+    pub base: Option<String>,
+    /// Deepest heading level listed in `headings`
+    pub toc_depth: u8,
+    pub search_index: bool,
+    pub search_json: bool,
+    pub base_url: Option<String>,
+    pub sitemap: bool,
+    pub feed: Option<FeedOptions>,
+    pub check_links: LinkCheck,
+    /// Extensions of files embedded as assets, `Some` also embeds files
+    /// referenced from pages
+    pub assets: Option<Vec<String>>,
+    /// Size in bytes under which images are inlined as data URIs
+    pub inline_assets_below: Option<u64>,
+    pub backend: Backend,
+    /// The `pandoc` executable or its directory
+    pub pandoc_path: Option<(String, Span)>,
+    pub pandoc_version: Option<(VersionReq, Span)>,
+    /// Configuration file the other arguments were completed from
+    pub config: Option<PathBuf>,
+    /// Directory relative paths are resolved against, the crate root
+    pub root: PathBuf,
+}
+
+impl Options {
+    /// Options with every optional argument at its default
+    pub(crate) fn new(mod_name: Ident, tree_name: Ident) -> Self {
+        Self {
+            mod_name,
+            tree_name,
+            content: Punctuated::new(),
+            source_ext: None,
+            input_format: None,
+            output_format: Vec::new(),
+            pandoc_options: Vec::new(),
+            nproc: 1,
+            metadata: None,
+            cache: true,
+            base: None,
+            toc_depth: 3,
+            search_index: false,
+            search_json: false,
+            base_url: None,
+            sitemap: false,
+            feed: None,
+            check_links: LinkCheck::Allow,
+            assets: None,
+            inline_assets_below: None,
+            backend: Backend::default(),
+            pandoc_path: None,
+            pandoc_version: None,
+            config: None,
+            root: crate::paths::manifest_dir(),
+        }
+    }
+
+    /// Options of the sources before any options file changes them
+    pub(crate) fn source_options(&self) -> SourceOptions {
+        SourceOptions {
+            input_format: self.input_format.clone(),
+            pandoc_options: self.pandoc_options.clone(),
+        }
+    }
 }
 
 impl Parse for Options {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        Self::parse_in(input, crate::paths::manifest_dir())
+    }
+}
+
+impl Options {
+    /// Parse the macro arguments, resolving relative paths against `root`
+    pub(crate) fn parse_in(input: syn::parse::ParseStream, root: PathBuf) -> syn::Result<Self> {
         // Parse arguments in any order
         let mut mod_name: Option<Ident> = None;
         let mut tree_name: Option<Ident> = None;
         let mut content: Option<Punctuated<Element, Token![,]>> = None;
         let mut input_format: Option<(InputFormat, Vec<MarkdownExtension>)> = None;
-        let mut output_format: Option<(OutputFormat, Vec<MarkdownExtension>)> = None;
+        let mut output_format: Vec<(OutputFormat, Vec<MarkdownExtension>)> = Vec::new();
         let mut source_ext: Option<String> = None;
         let mut pandoc_options: Option<Vec<PandocOption>> = None;
         let mut nproc: Option<usize> = None;
+        let mut metadata: Option<MetadataSchema> = None;
+        let mut cache: Option<bool> = None;
+        let mut base: Option<String> = None;
+        let mut toc_depth: Option<u8> = None;
+        let mut search_index: Option<bool> = None;
+        let mut search_json: Option<bool> = None;
+        let mut base_url: Option<String> = None;
+        let mut sitemap: Option<bool> = None;
+        let mut feed: Option<FeedOptions> = None;
+        let mut check_links: Option<LinkCheck> = None;
+        let mut assets: Option<Vec<String>> = None;
+        let mut inline_assets_below: Option<u64> = None;
+        let mut backend: Option<Backend> = None;
+        let mut pandoc_path: Option<(String, Span)> = None;
+        let mut pandoc_version: Option<(VersionReq, Span)> = None;
+        let mut config: Option<syn::LitStr> = None;
 
         // Helper function to parse format with extensions for input format
         while !input.is_empty() {
@@ -324,7 +476,7 @@ impl Parse for Options {
                 let _ = input.parse::<Token![=]>()?;
                 input_format = Some(parse_input_format(input)?);
             } else if lookahead.peek(keywords::output_format) {
-                if output_format.is_some() {
+                if !output_format.is_empty() {
                     return Err(syn::Error::new(
                         Span::call_site(),
                         "output_format specified multiple times",
@@ -332,7 +484,7 @@ impl Parse for Options {
                 }
                 let _ = input.parse::<keywords::output_format>()?;
                 let _ = input.parse::<Token![=]>()?;
-                output_format = Some(parse_output_format(input)?);
+                output_format = parse_output_formats(input)?;
             } else if lookahead.peek(keywords::options) {
                 if pandoc_options.is_some() {
                     return Err(syn::Error::new(
@@ -364,6 +516,180 @@ impl Parse for Options {
                         ));
                     }
                 }
+            } else if lookahead.peek(keywords::metadata) {
+                if metadata.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "metadata specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::metadata>()?;
+                let _ = input.parse::<Token![=]>()?;
+                metadata = Some(input.parse::<MetadataSchema>()?);
+            } else if lookahead.peek(keywords::cache) {
+                if cache.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "cache specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::cache>()?;
+                let _ = input.parse::<Token![=]>()?;
+                cache = Some(input.parse::<syn::LitBool>()?.value);
+            } else if lookahead.peek(keywords::base) {
+                if base.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "base specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::base>()?;
+                let _ = input.parse::<Token![=]>()?;
+                base = Some(input.parse::<syn::LitStr>()?.value());
+            } else if lookahead.peek(keywords::toc_depth) {
+                if toc_depth.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "toc_depth specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::toc_depth>()?;
+                let _ = input.parse::<Token![=]>()?;
+                let depth = input.parse::<syn::LitInt>()?;
+                match depth.base10_parse::<u8>() {
+                    Ok(n @ 1..=6) => toc_depth = Some(n),
+                    _ => return Err(syn::Error::new(depth.span(), "toc_depth must be between 1 and 6")),
+                }
+            } else if lookahead.peek(keywords::search_index) {
+                if search_index.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "search_index specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::search_index>()?;
+                let _ = input.parse::<Token![=]>()?;
+                search_index = Some(input.parse::<syn::LitBool>()?.value);
+            } else if lookahead.peek(keywords::search_json) {
+                if search_json.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "search_json specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::search_json>()?;
+                let _ = input.parse::<Token![=]>()?;
+                search_json = Some(input.parse::<syn::LitBool>()?.value);
+            } else if lookahead.peek(keywords::base_url) {
+                if base_url.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "base_url specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::base_url>()?;
+                let _ = input.parse::<Token![=]>()?;
+                base_url = Some(input.parse::<syn::LitStr>()?.value());
+            } else if lookahead.peek(keywords::sitemap) {
+                if sitemap.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "sitemap specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::sitemap>()?;
+                let _ = input.parse::<Token![=]>()?;
+                sitemap = Some(input.parse::<syn::LitBool>()?.value);
+            } else if lookahead.peek(keywords::feed) {
+                if feed.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "feed specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::feed>()?;
+                let _ = input.parse::<Token![=]>()?;
+                feed = Some(input.parse::<FeedOptions>()?);
+            } else if lookahead.peek(keywords::check_links) {
+                if check_links.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "check_links specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::check_links>()?;
+                let _ = input.parse::<Token![=]>()?;
+                check_links = Some(input.parse::<LinkCheck>()?);
+            } else if lookahead.peek(keywords::assets) {
+                if assets.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "assets specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::assets>()?;
+                let _ = input.parse::<Token![=]>()?;
+                let ext_stream;
+                bracketed!(ext_stream in input);
+                let exts = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&ext_stream)?;
+                assets = Some(
+                    exts.iter()
+                        .map(|ext| ext.value().trim_start_matches('.').to_string())
+                        .collect(),
+                );
+            } else if lookahead.peek(keywords::inline_assets_below) {
+                if inline_assets_below.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "inline_assets_below specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::inline_assets_below>()?;
+                let _ = input.parse::<Token![=]>()?;
+                inline_assets_below = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+            } else if lookahead.peek(keywords::backend) {
+                if backend.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "backend specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::backend>()?;
+                let _ = input.parse::<Token![=]>()?;
+                backend = Some(input.parse()?);
+            } else if lookahead.peek(keywords::pandoc_path) {
+                if pandoc_path.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "pandoc_path specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::pandoc_path>()?;
+                let _ = input.parse::<Token![=]>()?;
+                let path = input.parse::<syn::LitStr>()?;
+                pandoc_path = Some((path.value(), path.span()));
+            } else if lookahead.peek(keywords::pandoc_version) {
+                if pandoc_version.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "pandoc_version specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::pandoc_version>()?;
+                let _ = input.parse::<Token![=]>()?;
+                let req = input.parse::<syn::LitStr>()?;
+                let parsed = VersionReq::parse(&req.value()).map_err(|e| syn::Error::new(req.span(), e))?;
+                pandoc_version = Some((parsed, req.span()));
+            } else if lookahead.peek(keywords::config) {
+                if config.is_some() {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        "config specified multiple times",
+                    ));
+                }
+                let _ = input.parse::<keywords::config>()?;
+                let _ = input.parse::<Token![=]>()?;
+                config = Some(input.parse::<syn::LitStr>()?);
             } else {
                 return Err(lookahead.error());
             }
@@ -372,20 +698,62 @@ impl Parse for Options {
             let _ = input.parse::<Token![,]>();
         }
 
+        // Arguments given inline take precedence over the configuration file
+        let config_path = match &config {
+            Some(lit) => {
+                let path = crate::paths::resolve(&lit.value(), None, &root).map_err(|e| syn::Error::new(lit.span(), e))?;
+                let file = ConfigFile::load(&path, lit.span())?;
+                content = content.or_else(|| file.content.map(|elements| elements.into_iter().collect()));
+                source_ext = source_ext.or(file.source_ext);
+                input_format = input_format.or(file.input_format);
+                if output_format.is_empty() {
+                    output_format = file.output_format.unwrap_or_default();
+                }
+                pandoc_options = pandoc_options.or(file.pandoc_options);
+                nproc = nproc.or(file.nproc);
+                Some(path)
+            }
+            None => None,
+        };
+
         // Validate required arguments
         let mod_name = mod_name.ok_or_else(|| syn::Error::new(Span::call_site(), "mod_name is required"))?;
         let tree_name = tree_name.ok_or_else(|| syn::Error::new(Span::call_site(), "tree_name is required"))?;
         let content = content.ok_or_else(|| syn::Error::new(Span::call_site(), "content is required"))?;
+        let defaults = Self::new(mod_name, tree_name);
+        let sitemap = sitemap.unwrap_or(defaults.sitemap);
+        if base_url.is_none() && (sitemap || feed.is_some()) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "base_url is required by sitemap and feed",
+            ));
+        }
 
         Ok(Self {
-            mod_name,
-            tree_name,
             content,
             source_ext,
             input_format,
             output_format,
-            pandoc_options: pandoc_options.unwrap_or_default(),
-            nproc: nproc.unwrap_or(1),
+            pandoc_options: pandoc_options.unwrap_or(defaults.pandoc_options),
+            nproc: nproc.unwrap_or(defaults.nproc),
+            metadata,
+            cache: cache.unwrap_or(defaults.cache),
+            base,
+            toc_depth: toc_depth.unwrap_or(defaults.toc_depth),
+            search_index: search_index.unwrap_or(defaults.search_index),
+            search_json: search_json.unwrap_or(defaults.search_json),
+            base_url,
+            sitemap,
+            feed,
+            check_links: check_links.unwrap_or(defaults.check_links),
+            assets,
+            inline_assets_below,
+            backend: backend.unwrap_or(defaults.backend),
+            pandoc_path,
+            pandoc_version,
+            config: config_path,
+            root,
+            ..defaults
         })
     }
 }
@@ -446,7 +814,7 @@ mod tests {
             "expected markdown extensions to match"
         );
 
-        let (out_fmt, out_exts) = opts.output_format.expect("output_format missing");
+        let (out_fmt, out_exts) = opts.output_format.into_iter().next().expect("output_format missing");
         assert_eq!(
             out_fmt.to_string(),
             "html",
@@ -545,7 +913,7 @@ mod tests {
             let src = build_base_options(&section);
             let opts = syn::parse_str::<Options>(&src)
                 .unwrap_or_else(|e| panic!("parse failed for {}: {}", ident, e));
-            let (fmt, _exts) = opts.output_format.expect("missing output_format");
+            let (fmt, _exts) = opts.output_format.into_iter().next().expect("missing output_format");
             let got = fmt.to_string().replace('_', "").to_lowercase();
             let want = camel_to_snake(&ident_token).replace('_', "").to_lowercase();
             assert_eq!(got, want, "output_format mismatch for {}", ident);
@@ -679,11 +1047,165 @@ nproc = 4
             vec!["smart".to_string(), "raw_html".to_string()]
         );
 
-        let (out_fmt, out_exts) = opts.output_format.expect("output_format missing");
+        let (out_fmt, out_exts) = opts.output_format.into_iter().next().expect("output_format missing");
         assert_eq!(out_fmt.to_string(), "html");
         assert!(out_exts.is_empty());
 
         assert_eq!(opts.pandoc_options.len(), 0);
         assert_eq!(opts.nproc, 4);
+        assert!(opts.metadata.is_none());
+        assert!(opts.cache);
+    }
+
+    #[test]
+    fn output_format_list_is_parsed_in_order() {
+        let src = build_base_options("output_format = [Html5[Smart], Plain, Latex]");
+        let opts = syn::parse_str::<Options>(&src).expect("failed to parse output formats");
+        let names: Vec<String> = opts.output_format.iter().map(|(f, _)| f.to_string()).collect();
+        assert_eq!(names, vec!["html5", "plain", "latex"]);
+        assert_eq!(opts.output_format[0].1.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_output_format_fails() {
+        let src = build_base_options("output_format = [Html5, Plain, Html5]");
+        let err = syn::parse_str::<Options>(&src).err().expect("duplicate format should fail");
+        assert!(err.to_string().contains("`html5` listed multiple times"));
+
+        let src = build_base_options("output_format = []");
+        assert!(syn::parse_str::<Options>(&src).is_err());
+    }
+
+    #[test]
+    fn toc_depth_is_parsed_and_checked() {
+        let opts = syn::parse_str::<Options>(&build_base_options("toc_depth = 2")).unwrap();
+        assert_eq!(opts.toc_depth, 2);
+
+        let opts = syn::parse_str::<Options>(&build_base_options("source_ext = \"md\"")).unwrap();
+        assert_eq!(opts.toc_depth, 3);
+
+        let err = syn::parse_str::<Options>(&build_base_options("toc_depth = 7")).err().unwrap();
+        assert_eq!(err.to_string(), "toc_depth must be between 1 and 6");
+    }
+
+    #[test]
+    fn search_flags_default_to_off() {
+        let opts = syn::parse_str::<Options>(&build_base_options("search_index = true")).unwrap();
+        assert!(opts.search_index);
+        assert!(!opts.search_json);
+    }
+
+    #[test]
+    fn sitemap_and_feed_require_base_url() {
+        let err = syn::parse_str::<Options>(&build_base_options("sitemap = true")).err().unwrap();
+        assert!(err.to_string().contains("base_url is required"));
+
+        let src = build_base_options(
+            "base_url = \"https://example.com\", sitemap = true, feed = { title: \"Blog\", limit: 3 }",
+        );
+        let opts = syn::parse_str::<Options>(&src).expect("failed to parse sitemap and feed");
+        assert_eq!(opts.base_url.as_deref(), Some("https://example.com"));
+        assert!(opts.sitemap);
+        assert_eq!(opts.feed.map(|f| f.limit), Some(3));
+    }
+
+    #[test]
+    fn check_links_defaults_to_allow() {
+        let opts = syn::parse_str::<Options>(&build_base_options("cache = true")).unwrap();
+        assert_eq!(opts.check_links, LinkCheck::Allow);
+
+        let opts = syn::parse_str::<Options>(&build_base_options("check_links = warn")).unwrap();
+        assert_eq!(opts.check_links, LinkCheck::Warn);
+    }
+
+    #[test]
+    fn backend_defaults_to_pandoc() {
+        let opts = syn::parse_str::<Options>(&build_base_options("cache = true")).unwrap();
+        assert_eq!(opts.backend, Backend::default());
+
+        let opts = syn::parse_str::<Options>(&build_base_options("backend = Pandoc")).unwrap();
+        assert_eq!(opts.backend, Backend::Pandoc);
+    }
+
+    #[test]
+    fn pandoc_version_is_validated() {
+        let opts = syn::parse_str::<Options>(&build_base_options("pandoc_version = \">=3.1\"")).unwrap();
+        assert_eq!(opts.pandoc_version.map(|(req, _)| req), Some(VersionReq::parse(">=3.1").unwrap()));
+
+        let err = syn::parse_str::<Options>(&build_base_options("pandoc_version = \"latest\"")).err().unwrap();
+        assert!(err.to_string().contains("invalid pandoc version requirement"));
+    }
+
+    #[test]
+    fn inline_arguments_override_the_config_file() {
+        let path = std::env::temp_dir().join(format!("pandoc-generator-options-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "nproc = 6\nsource_ext = \"md\"\noutput_format = [\"Html5\", \"Plain\"]\noptions = [\"Standalone\"]\n\n[[content]]\npath = \"docs\"\n",
+        )
+        .unwrap();
+
+        let src = format!("mod_name = m, tree_name = T, config = {:?}, nproc = 2, options = []", path.display().to_string());
+        let opts = syn::parse_str::<Options>(&src).unwrap();
+        assert_eq!(opts.nproc, 2);
+        assert!(opts.pandoc_options.is_empty());
+        assert_eq!(opts.source_ext.as_deref(), Some("md"));
+        assert_eq!(opts.output_format.len(), 2);
+        assert!(matches!(opts.content.first(), Some(Element::CompileFromPath { path: docs, .. }) if *docs == std::env::temp_dir().join("docs").display().to_string()));
+        assert_eq!(opts.config, Some(path.clone()));
+
+        let missing = syn::parse_str::<Options>("mod_name = m, tree_name = T, config = \"no/such/file.toml\"").err().unwrap();
+        assert!(missing.to_string().starts_with("Failed to read"), "{}", missing);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn asset_extensions_are_parsed() {
+        let opts = syn::parse_str::<Options>(&build_base_options("assets = [\"svg\", \".png\"]")).unwrap();
+        assert_eq!(opts.assets, Some(vec!["svg".to_string(), "png".to_string()]));
+
+        let opts = syn::parse_str::<Options>(&build_base_options("assets = []")).unwrap();
+        assert_eq!(opts.assets, Some(vec![]));
+        assert_eq!(opts.inline_assets_below, None);
+
+        let opts = syn::parse_str::<Options>(&build_base_options("inline_assets_below = 8_192")).unwrap();
+        assert_eq!(opts.inline_assets_below, Some(8192));
+    }
+
+    #[test]
+    fn base_is_parsed() {
+        let src = build_base_options("base = \"$DOCS_ROOT/site\"");
+        let opts = syn::parse_str::<Options>(&src).expect("failed to parse base");
+        assert_eq!(opts.base.as_deref(), Some("$DOCS_ROOT/site"));
+    }
+
+    #[test]
+    fn cache_can_be_disabled() {
+        let src = build_base_options("cache = false");
+        let opts = syn::parse_str::<Options>(&src).expect("failed to parse cache flag");
+        assert!(!opts.cache);
+    }
+
+    #[test]
+    fn metadata_schema_is_parsed() {
+        let src = build_base_options(
+            "metadata = { title: String, date: Date, tags: Vec<String>, weight: Option<u32> }",
+        );
+        let opts = syn::parse_str::<Options>(&src).expect("failed to parse metadata schema");
+        let names: Vec<String> = opts
+            .metadata
+            .expect("metadata missing")
+            .fields
+            .iter()
+            .map(|f| f.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["title", "date", "tags", "weight"]);
+    }
+
+    #[test]
+    fn metadata_specified_twice_fails() {
+        let src = build_base_options("metadata = { title: String }, metadata = { date: Date }");
+        let err = syn::parse_str::<Options>(&src).err().expect("expected an error");
+        assert!(err.to_string().contains("metadata specified multiple times"));
     }
 }
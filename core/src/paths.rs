@@ -0,0 +1,152 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Default root relative content paths are resolved against: the manifest
+/// directory of the crate invoking the macro.
+pub(crate) fn manifest_dir() -> PathBuf {
+    env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+/// Expand `$VAR` and `${VAR}` references using `lookup`; `$$` is a literal `$`
+fn expand_vars(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+
+        let name = match chars.peek() {
+            Some('$') => {
+                chars.next();
+                out.push('$');
+                continue;
+            }
+            Some('{') => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unterminated `${{` in {:?}", input)),
+                    }
+                }
+                name
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                name
+            }
+        };
+
+        if name.is_empty() {
+            return Err(format!("Empty variable name in {:?}", input));
+        }
+
+        let value = lookup(&name)
+            .ok_or_else(|| format!("Environment variable `{}` used in {:?} is not set", name, input))?;
+        out.push_str(&value);
+    }
+
+    Ok(out)
+}
+
+fn resolve_with(
+    path: &str,
+    base: Option<&str>,
+    root: &Path,
+    lookup: impl Fn(&str) -> Option<String> + Copy,
+) -> Result<PathBuf, String> {
+    let path = PathBuf::from(expand_vars(path, lookup)?);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+
+    let base = match base {
+        Some(base) => root.join(expand_vars(base, lookup)?),
+        None => root.to_path_buf(),
+    };
+
+    Ok(base.join(path))
+}
+
+/// Resolve a content path: expand environment variables, then join relative
+/// paths onto `base` (itself relative to `root`).
+pub(crate) fn resolve(path: &str, base: Option<&str>, root: &Path) -> Result<PathBuf, String> {
+    resolve_with(path, base, root, |name| env::var(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "DOCS" => Some("/srv/docs".to_string()),
+            "LANG_DIR" => Some("en".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_plain_and_braced_vars() {
+        assert_eq!(expand_vars("$DOCS/guide", lookup).unwrap(), "/srv/docs/guide");
+        assert_eq!(expand_vars("content/${LANG_DIR}_v2", lookup).unwrap(), "content/en_v2");
+        assert_eq!(expand_vars("cost$$", lookup).unwrap(), "cost$");
+    }
+
+    #[test]
+    fn missing_var_is_an_error() {
+        let err = expand_vars("$NOPE/x", lookup).unwrap_err();
+        assert!(err.contains("`NOPE`"));
+        assert!(expand_vars("${DOCS", lookup).is_err());
+        assert!(expand_vars("a/$/b", lookup).is_err());
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_root() {
+        let root = Path::new("/work/crate");
+        assert_eq!(
+            resolve_with("tests/assets", None, root, lookup).unwrap(),
+            PathBuf::from("/work/crate/tests/assets")
+        );
+    }
+
+    #[test]
+    fn base_is_relative_to_root() {
+        let root = Path::new("/work/crate");
+        assert_eq!(
+            resolve_with("guide", Some("../site/$LANG_DIR"), root, lookup).unwrap(),
+            PathBuf::from("/work/crate/../site/en/guide")
+        );
+        assert_eq!(
+            resolve_with("guide", Some("$DOCS"), root, lookup).unwrap(),
+            PathBuf::from("/srv/docs/guide")
+        );
+    }
+
+    #[test]
+    fn absolute_paths_are_kept() {
+        let root = Path::new("/work/crate");
+        assert_eq!(
+            resolve_with("$DOCS/a.md", Some("base"), root, lookup).unwrap(),
+            PathBuf::from("/srv/docs/a.md")
+        );
+    }
+}
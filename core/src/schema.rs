@@ -0,0 +1,371 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::{
+    GenericArgument, Ident, PathArguments, Token, Type, braced,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+};
+
+use crate::{ast::MetaValue, error::Errors};
+
+/// Type of a single metadata field as declared in `metadata = { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SchemaType {
+    String,
+    Bool,
+    Int(Ident),
+    Date,
+    Vec(Box<SchemaType>),
+    Option(Box<SchemaType>),
+}
+
+const INT_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize",
+];
+
+impl SchemaType {
+    fn from_type(ty: &Type) -> syn::Result<Self> {
+        let unsupported = || {
+            syn::Error::new_spanned(
+                ty,
+                "Unsupported metadata type (expected String, bool, an integer type, Date, Vec<T> or Option<T>)",
+            )
+        };
+
+        let Type::Path(path) = ty else {
+            return Err(unsupported());
+        };
+        let segment = path.path.segments.last().ok_or_else(unsupported)?;
+
+        let inner = || match &segment.arguments {
+            PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+                GenericArgument::Type(inner) => Self::from_type(inner),
+                _ => Err(unsupported()),
+            },
+            _ => Err(unsupported()),
+        };
+
+        let name = segment.ident.to_string();
+        match name.as_str() {
+            "String" => Ok(SchemaType::String),
+            "bool" => Ok(SchemaType::Bool),
+            "Date" => Ok(SchemaType::Date),
+            "Vec" => Ok(SchemaType::Vec(Box::new(inner()?))),
+            "Option" => Ok(SchemaType::Option(Box::new(inner()?))),
+            n if INT_TYPES.contains(&n) => Ok(SchemaType::Int(segment.ident.clone())),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Rust type of the field in the generated `Metadata` struct
+    pub(crate) fn rust_type(&self) -> TokenStream {
+        match self {
+            SchemaType::String => quote! { &'static str },
+            SchemaType::Bool => quote! { bool },
+            SchemaType::Int(ty) => quote! { #ty },
+            SchemaType::Date => quote! { Date },
+            SchemaType::Vec(inner) => {
+                let inner = inner.rust_type();
+                quote! { &'static [#inner] }
+            }
+            SchemaType::Option(inner) => {
+                let inner = inner.rust_type();
+                quote! { Option<#inner> }
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            SchemaType::String => "String".to_string(),
+            SchemaType::Bool => "bool".to_string(),
+            SchemaType::Int(ty) => ty.to_string(),
+            SchemaType::Date => "Date".to_string(),
+            SchemaType::Vec(inner) => format!("Vec<{}>", inner.describe()),
+            SchemaType::Option(inner) => format!("Option<{}>", inner.describe()),
+        }
+    }
+
+    /// Convert a metadata value into a constant expression of this type
+    fn convert(&self, value: &MetaValue) -> Result<TokenStream, String> {
+        let mismatch = || format!("expected {}, found {}", self.describe(), describe_value(value));
+
+        match (self, value) {
+            (SchemaType::Option(inner), value) => {
+                let value = inner.convert(value)?;
+                Ok(quote! { Some(#value) })
+            }
+            (SchemaType::String, MetaValue::String(s)) => Ok(quote! { #s }),
+            (SchemaType::Bool, MetaValue::Bool(b)) => Ok(quote! { #b }),
+            (SchemaType::Int(ty), MetaValue::String(s)) => {
+                let n = s.trim().parse::<i128>().map_err(|_| mismatch())?;
+                if !int_fits(&ty.to_string(), n) {
+                    return Err(format!("value {} does not fit in {}", n, ty));
+                }
+                let lit = Literal::i128_unsuffixed(n);
+                Ok(quote! { #lit })
+            }
+            (SchemaType::Date, MetaValue::String(s)) => {
+                let (year, month, day) = parse_date(s)?;
+                Ok(quote! { Date { year: #year, month: #month, day: #day } })
+            }
+            (SchemaType::Vec(inner), MetaValue::List(items)) => {
+                let items = items
+                    .iter()
+                    .map(|i| inner.convert(i))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(quote! { &[#(#items),*] })
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+fn describe_value(value: &MetaValue) -> String {
+    match value {
+        MetaValue::String(s) => format!("string {:?}", s),
+        MetaValue::Bool(b) => format!("bool {}", b),
+        MetaValue::List(_) => "list".to_string(),
+        MetaValue::Map(_) => "map".to_string(),
+    }
+}
+
+fn int_fits(ty: &str, n: i128) -> bool {
+    let (min, max): (i128, i128) = match ty {
+        "u8" => (0, u8::MAX as i128),
+        "u16" => (0, u16::MAX as i128),
+        "u32" => (0, u32::MAX as i128),
+        "u64" => (0, u64::MAX as i128),
+        "usize" => (0, usize::MAX as i128),
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        "i64" => (i64::MIN as i128, i64::MAX as i128),
+        "isize" => (isize::MIN as i128, isize::MAX as i128),
+        _ => return false,
+    };
+    (min..=max).contains(&n)
+}
+
+/// Parse `YYYY-MM-DD`, optionally followed by a time part
+pub(crate) fn parse_date(s: &str) -> Result<(i32, u8, u8), String> {
+    let invalid = || format!("invalid date {:?} (expected YYYY-MM-DD)", s);
+    let date = s.trim().split(['T', ' ']).next().ok_or_else(invalid)?;
+    let mut parts = date.splitn(3, '-');
+
+    let year = parts.next().and_then(|p| p.parse::<i32>().ok()).ok_or_else(invalid)?;
+    let month = parts.next().and_then(|p| p.parse::<u8>().ok()).ok_or_else(invalid)?;
+    let day = parts.next().and_then(|p| p.parse::<u8>().ok()).ok_or_else(invalid)?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    Ok((year, month, day))
+}
+
+pub(crate) struct SchemaField {
+    pub name: Ident,
+    pub ty: SchemaType,
+}
+
+/// User-declared metadata schema: `metadata = { title: String, tags: Vec<String> }`
+pub(crate) struct MetadataSchema {
+    pub fields: Vec<SchemaField>,
+}
+
+impl Parse for MetadataSchema {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        braced!(content in input);
+
+        let fields = Punctuated::<(Ident, Type), Token![,]>::parse_terminated_with(
+            &content,
+            |field| {
+                let name = field.parse::<Ident>()?;
+                let _ = field.parse::<Token![:]>()?;
+                Ok((name, field.parse::<Type>()?))
+            },
+        )?
+        .into_iter()
+        .map(|(name, ty)| {
+            Ok(SchemaField {
+                name,
+                ty: SchemaType::from_type(&ty)?,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(Self { fields })
+    }
+}
+
+impl MetadataSchema {
+    /// Definition of the generated `Metadata` struct
+    pub(crate) fn struct_definition(&self) -> TokenStream {
+        let fields = self.fields.iter().map(|f| {
+            let name = &f.name;
+            let ty = f.ty.rust_type();
+            quote! { pub(crate) #name: #ty }
+        });
+
+        quote! {
+            /// Typed front matter declared with the `metadata` argument
+            #[derive(Debug, PartialEq, Eq)]
+            pub(crate) struct Metadata {
+                #(#fields),*
+            }
+        }
+    }
+
+    /// Build the `Metadata { ... }` literal for one page, reporting every
+    /// missing or mistyped field as an error spanned to its declaration.
+    pub(crate) fn instantiate(&self, meta: &MetaValue, file: &str) -> syn::Result<TokenStream> {
+        let mut values = Vec::new();
+        let mut errors = Errors::default();
+
+        for field in self.fields.iter() {
+            let key = field.name.to_string();
+            let value = match (meta_get(meta, &key), &field.ty) {
+                (None, SchemaType::Option(_)) => Ok(quote! { None }),
+                (None, _) => Err(format!("missing required metadata key `{}`", key)),
+                (Some(value), ty) => ty
+                    .convert(value)
+                    .map_err(|e| format!("metadata key `{}`: {}", key, e)),
+            };
+
+            match value {
+                Ok(value) => {
+                    let name = &field.name;
+                    values.push(quote! { #name: #value });
+                }
+                Err(msg) => errors.push(syn::Error::new_spanned(
+                    &field.name,
+                    format!("{}: {}", file, msg),
+                )),
+            }
+        }
+
+        errors.finish()?;
+        Ok(quote! { Metadata { #(#values),* } })
+    }
+}
+
+fn meta_get<'a>(meta: &'a MetaValue, key: &str) -> Option<&'a MetaValue> {
+    match meta {
+        MetaValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(src: &str) -> MetadataSchema {
+        syn::parse_str::<MetadataSchema>(src).expect("failed to parse schema")
+    }
+
+    fn meta(entries: Vec<(&str, MetaValue)>) -> MetaValue {
+        MetaValue::Map(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    fn string(s: &str) -> MetaValue {
+        MetaValue::String(s.to_string())
+    }
+
+    #[test]
+    fn parse_all_supported_types() {
+        let s = schema(
+            "{ title: String, draft: bool, weight: u32, date: Date, tags: Vec<String>, extra: Option<Vec<i64>> }",
+        );
+        let types: Vec<SchemaType> = s.fields.into_iter().map(|f| f.ty).collect();
+        assert_eq!(
+            types,
+            vec![
+                SchemaType::String,
+                SchemaType::Bool,
+                SchemaType::Int(syn::parse_str("u32").unwrap()),
+                SchemaType::Date,
+                SchemaType::Vec(Box::new(SchemaType::String)),
+                SchemaType::Option(Box::new(SchemaType::Vec(Box::new(SchemaType::Int(
+                    syn::parse_str("i64").unwrap()
+                ))))),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_type_fails() {
+        let err = syn::parse_str::<MetadataSchema>("{ ratio: f64 }").err().unwrap();
+        assert!(err.to_string().contains("Unsupported metadata type"));
+    }
+
+    #[test]
+    fn instantiate_full_metadata() {
+        let s = schema("{ title: String, date: Date, tags: Vec<String>, weight: Option<u32> }");
+        let m = meta(vec![
+            ("title", string("Hello")),
+            ("date", string("2024-05-01")),
+            ("tags", MetaValue::List(vec![string("a"), string("b")])),
+            ("weight", string("3")),
+        ]);
+        let code = s.instantiate(&m, "post.md").unwrap().to_string();
+        assert_eq!(
+            code,
+            quote! {
+                Metadata {
+                    title: "Hello",
+                    date: Date { year: 2024i32, month: 5u8, day: 1u8 },
+                    tags: &["a", "b"],
+                    weight: Some(3)
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn missing_optional_field_is_none() {
+        let s = schema("{ weight: Option<u32> }");
+        let code = s.instantiate(&meta(vec![]), "post.md").unwrap().to_string();
+        assert_eq!(code, quote! { Metadata { weight: None } }.to_string());
+    }
+
+    #[test]
+    fn missing_required_field_names_file() {
+        let s = schema("{ title: String }");
+        let err = s.instantiate(&meta(vec![]), "docs/post.md").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "docs/post.md: missing required metadata key `title`"
+        );
+    }
+
+    #[test]
+    fn wrong_type_is_reported() {
+        let s = schema("{ draft: bool, weight: u8 }");
+        let m = meta(vec![("draft", string("yes")), ("weight", string("300"))]);
+        let errors: Vec<String> = s
+            .instantiate(&m, "post.md")
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "post.md: metadata key `draft`: expected bool, found string \"yes\"".to_string(),
+                "post.md: metadata key `weight`: value 300 does not fit in u8".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn dates_are_validated() {
+        assert_eq!(parse_date("2024-05-01"), Ok((2024, 5, 1)));
+        assert_eq!(parse_date("2024-05-01T10:00:00Z"), Ok((2024, 5, 1)));
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+}
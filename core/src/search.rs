@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde_json::{Map, Value, json};
+
+/// Split text into lowercase alphanumeric terms. The generated `search`
+/// function tokenizes queries the same way.
+pub(crate) fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// One indexed page, in reading order
+pub(crate) struct SearchDocument {
+    pub title: String,
+    pub route: Option<String>,
+    pub text: String,
+}
+
+/// Inverted index from term to the pages containing it, with the word
+/// positions of every occurrence
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    terms: BTreeMap<String, Vec<(usize, Vec<u32>)>>,
+    /// Number of terms in each page
+    lengths: Vec<usize>,
+}
+
+impl SearchIndex {
+    pub(crate) fn build(documents: &[SearchDocument]) -> Self {
+        let mut index = Self::default();
+
+        for (doc, document) in documents.iter().enumerate() {
+            let text = format!("{} {}", document.title, document.text);
+            let mut length = 0;
+
+            for (position, term) in tokenize(&text).enumerate() {
+                let postings = index.terms.entry(term).or_default();
+                match postings.last_mut() {
+                    Some((last, positions)) if *last == doc => positions.push(position as u32),
+                    _ => postings.push((doc, vec![position as u32])),
+                }
+                length += 1;
+            }
+
+            index.lengths.push(length);
+        }
+
+        index
+    }
+
+    /// `SEARCH_INDEX` table and the `search` function over it
+    pub(crate) fn generate(&self) -> TokenStream {
+        let terms = self.terms.iter().map(|(term, postings)| {
+            let postings = postings
+                .iter()
+                .map(|(doc, positions)| quote! { (#doc, &[#(#positions),*]) });
+            quote! { (#term, &[#(#postings),*]) }
+        });
+
+        quote! {
+            /// Inverted index: every term with the reading-order index of the
+            /// pages containing it and the word positions within each page,
+            /// sorted by term
+            #[allow(dead_code)]
+            pub(crate) const SEARCH_INDEX: &[(&str, &[(usize, &[u32])])] = &[
+                #(#terms),*
+            ];
+
+            /// Pages matching any term of `query`, best match first. Scores
+            /// are tf-idf sums over the query terms.
+            #[allow(dead_code)]
+            pub(crate) fn search(query: &str) -> std::vec::Vec<(&'static ContentTree, f32)> {
+                let mut scores = vec![0.0f32; READING_ORDER.len()];
+
+                let terms = query
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase);
+
+                for term in terms {
+                    let Ok(i) = SEARCH_INDEX.binary_search_by(|(t, _)| (*t).cmp(term.as_str())) else {
+                        continue;
+                    };
+                    let postings = SEARCH_INDEX[i].1;
+                    let idf = (1.0 + READING_ORDER.len() as f32 / postings.len() as f32).ln();
+
+                    for (doc, positions) in postings {
+                        scores[*doc] += (positions.len() as f32).sqrt() * idf;
+                    }
+                }
+
+                let mut hits: std::vec::Vec<_> = scores
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, score)| *score > 0.0)
+                    .filter_map(|(doc, score)| Some((ContentTree::node_at(READING_ORDER[doc])?, score)))
+                    .collect();
+                hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+                hits
+            }
+        }
+    }
+
+    /// Serialized elasticlunr index over a single `body` field, with an empty
+    /// pipeline so the browser tokenizes queries like `search` does
+    pub(crate) fn to_elasticlunr_json(&self, documents: &[SearchDocument]) -> String {
+        let mut root = TrieNode::default();
+        for (term, postings) in &self.terms {
+            let node = term.chars().fold(&mut root, |node, c| node.children.entry(c).or_default());
+            node.docs = postings
+                .iter()
+                .map(|(doc, positions)| (doc.to_string(), (positions.len() as f64).sqrt()))
+                .collect();
+        }
+
+        let mut docs = Map::new();
+        let mut doc_info = Map::new();
+        for (doc, document) in documents.iter().enumerate() {
+            docs.insert(
+                doc.to_string(),
+                json!({ "id": doc.to_string(), "title": document.title, "route": document.route }),
+            );
+            doc_info.insert(doc.to_string(), json!({ "body": self.lengths[doc] }));
+        }
+
+        json!({
+            "version": "0.9.5",
+            "fields": ["body"],
+            "ref": "id",
+            "documentStore": {
+                "docs": docs,
+                "docInfo": doc_info,
+                "length": documents.len(),
+                "save": true
+            },
+            "index": { "body": { "root": root.to_json() } },
+            "pipeline": []
+        })
+        .to_string()
+    }
+}
+
+/// Character trie used by elasticlunr's inverted index
+#[derive(Default)]
+struct TrieNode {
+    docs: Vec<(String, f64)>,
+    children: BTreeMap<char, TrieNode>,
+}
+
+impl TrieNode {
+    fn to_json(&self) -> Value {
+        let mut node = Map::new();
+        let docs: Map<String, Value> = self
+            .docs
+            .iter()
+            .map(|(doc, tf)| (doc.clone(), json!({ "tf": tf })))
+            .collect();
+
+        node.insert("df".to_string(), json!(docs.len()));
+        node.insert("docs".to_string(), Value::Object(docs));
+        for (c, child) in &self.children {
+            node.insert(c.to_string(), child.to_json());
+        }
+
+        Value::Object(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(title: &str, text: &str) -> SearchDocument {
+        SearchDocument {
+            title: title.to_string(),
+            route: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn tokenize_splits_and_lowercases() {
+        let terms: Vec<_> = tokenize("Hello, World! it's pandoc-2").collect();
+        assert_eq!(terms, vec!["hello", "world", "it", "s", "pandoc", "2"]);
+    }
+
+    #[test]
+    fn index_records_pages_and_positions() {
+        let index = SearchIndex::build(&[
+            document("Intro", "Rust macros expand rust code"),
+            document("Other", "Nothing here"),
+        ]);
+
+        assert_eq!(index.terms["rust"], vec![(0, vec![1, 4])]);
+        assert_eq!(index.terms["other"], vec![(1, vec![0])]);
+        assert_eq!(index.lengths, vec![6, 3]);
+    }
+
+    #[test]
+    fn elasticlunr_json_has_a_trie_per_term() {
+        let documents = [document("Go", "go")];
+        let index = SearchIndex::build(&documents);
+        let json: Value = serde_json::from_str(&index.to_elasticlunr_json(&documents)).unwrap();
+
+        let node = &json["index"]["body"]["root"]["g"]["o"];
+        assert_eq!(node["df"], 1);
+        assert_eq!(node["docs"]["0"]["tf"], 2f64.sqrt());
+        assert_eq!(json["documentStore"]["docInfo"]["0"]["body"], 2);
+        assert_eq!(json["documentStore"]["docs"]["0"]["title"], "Go");
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    fs, io, mem,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use pandoc::{InputFormat, MarkdownExtension, PandocOption};
+use proc_macro2::Span;
+use syn::parse::{ParseStream, Parser};
+use toml_edit::{Item, Value};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{
+    config::{Source, format_syntax, parse_option},
+    error::Errors,
+    options::{parse_input_format, parse_markdown_extensions},
+};
+
+/// Names of the per-directory options files, a directory may have one
+pub(crate) const OPTIONS_FILES: [&str; 2] = ["__options__.toml", "_dir.yaml"];
+
+/// Keys an options file may set
+const KEYS: &[&str] = &["input_format", "extensions", "options"];
+
+/// Input format and pandoc options a source is converted with: the macro's,
+/// changed by the options file of every directory above the source
+#[derive(Debug, Clone, Default)]
+pub struct SourceOptions {
+    pub input_format: Option<(InputFormat, Vec<MarkdownExtension>)>,
+    pub pandoc_options: Vec<PandocOption>,
+}
+
+/// One setting of an options file, still in the macro's syntax
+enum Setting {
+    InputFormat(String),
+    Extensions(Vec<String>),
+    Options(Vec<String>),
+}
+
+/// A setting with its key and position in the file
+type Entry = (String, Option<Range<usize>>, Setting);
+
+/// What an options file changes
+#[derive(Default)]
+struct Overrides {
+    input_format: Option<(InputFormat, Vec<MarkdownExtension>)>,
+    extensions: Vec<MarkdownExtension>,
+    pandoc_options: Vec<PandocOption>,
+}
+
+impl SourceOptions {
+    /// Options of the sources under `dir`: these ones with its options file
+    /// applied, and that file if there is one
+    pub(crate) fn enter(&self, dir: &Path) -> io::Result<(Self, Option<PathBuf>)> {
+        let found: Vec<PathBuf> = OPTIONS_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect();
+        let path = match found.as_slice() {
+            [] => return Ok((self.clone(), None)),
+            [path] => path.clone(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} has both {} and {}, keep one", dir, OPTIONS_FILES[0], OPTIONS_FILES[1]),
+                ));
+            }
+        };
+
+        let text = fs::read_to_string(&path)?;
+        let display = path.display().to_string();
+        let source = Source {
+            path: &display,
+            text: &text,
+            span: Span::call_site(),
+        };
+        let entries = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => read_toml(&source),
+            _ => read_yaml(&source),
+        };
+        let mut overrides = entries.and_then(|(entries, errors)| Overrides::parse(&source, entries, errors)).map_err(|err| {
+            let messages: Vec<String> = err.into_iter().map(|e| e.to_string()).collect();
+            io::Error::new(io::ErrorKind::InvalidData, messages.join("; "))
+        })?;
+        for option in &mut overrides.pandoc_options {
+            rebase(option, dir);
+        }
+
+        Ok((self.merge(overrides), Some(path)))
+    }
+
+    /// Apply an options file: its input format replaces the inherited one,
+    /// its extensions are added to it and its options replace inherited
+    /// options of the same kind, except those pandoc accepts repeatedly
+    fn merge(&self, overrides: Overrides) -> Self {
+        let mut input_format = overrides.input_format.or_else(|| self.input_format.clone());
+        if !overrides.extensions.is_empty() {
+            let (_, extensions) = input_format.get_or_insert_with(|| (InputFormat::Markdown, Vec::new()));
+            for extension in overrides.extensions {
+                if !extensions.iter().any(|e| e.to_string() == extension.to_string()) {
+                    extensions.push(extension);
+                }
+            }
+        }
+
+        let mut pandoc_options = self.pandoc_options.clone();
+        for option in overrides.pandoc_options {
+            pandoc_options.retain(|inherited| !replaces(&option, inherited));
+            pandoc_options.push(option);
+        }
+
+        Self {
+            input_format,
+            pandoc_options,
+        }
+    }
+
+    /// Prefix the HTML writer adds to heading ids
+    pub(crate) fn id_prefix(&self) -> &str {
+        self.pandoc_options
+            .iter()
+            .find_map(|opt| match opt {
+                PandocOption::IdPrefix(prefix) => Some(prefix.as_str()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Make a relative file in `option` relative to `dir`, the directory of the
+/// options or configuration file setting it. Filters that are not there keep their name, for
+/// pandoc to look up in its data directory and on PATH.
+pub(crate) fn rebase(option: &mut PandocOption, dir: &Path) {
+    let path = match option {
+        PandocOption::Filter(path) | PandocOption::LuaFilter(path) if !dir.join(&*path).is_file() => return,
+        PandocOption::DataDir(path)
+        | PandocOption::Defaults(path)
+        | PandocOption::Filter(path)
+        | PandocOption::LuaFilter(path)
+        | PandocOption::ExtractMedia(path)
+        | PandocOption::Template(path)
+        | PandocOption::IncludeInHeader(path)
+        | PandocOption::IncludeBeforeBody(path)
+        | PandocOption::IncludeAfterBody(path)
+        | PandocOption::ReferenceOdt(path)
+        | PandocOption::ReferenceDoc(path)
+        | PandocOption::EpubStylesheet(path)
+        | PandocOption::EpubCoverImage(path)
+        | PandocOption::EpubMetadata(path)
+        | PandocOption::EpubEmbedFont(path)
+        | PandocOption::Bibliography(path)
+        | PandocOption::Csl(path) => path,
+        _ => return,
+    };
+
+    if path.is_relative() {
+        *path = dir.join(&*path);
+    }
+}
+
+/// Whether `option` takes the place of `inherited` rather than adding to it
+fn replaces(option: &PandocOption, inherited: &PandocOption) -> bool {
+    match (option, inherited) {
+        (PandocOption::Meta(key, _), PandocOption::Meta(other, _))
+        | (PandocOption::Var(key, _), PandocOption::Var(other, _)) => key == other,
+        (
+            PandocOption::Filter(_)
+            | PandocOption::LuaFilter(_)
+            | PandocOption::IncludeInHeader(_)
+            | PandocOption::IncludeBeforeBody(_)
+            | PandocOption::IncludeAfterBody(_)
+            | PandocOption::Bibliography(_)
+            | PandocOption::Css(_),
+            _,
+        ) => false,
+        _ => mem::discriminant(option) == mem::discriminant(inherited),
+    }
+}
+
+impl Overrides {
+    /// Parse the settings read from an options file, adding to the errors
+    /// found while reading it
+    fn parse(source: &Source, entries: Vec<Entry>, mut errors: Errors) -> syn::Result<Self> {
+        let mut overrides = Overrides::default();
+
+        for (key, range, setting) in entries {
+            let result = match setting {
+                Setting::InputFormat(format) => parse_input_format
+                    .parse_str(&format)
+                    .map(|format| overrides.input_format = Some(format))
+                    .map_err(|e| e.to_string()),
+                Setting::Extensions(names) => (|input: ParseStream| parse_markdown_extensions(&input))
+                    .parse_str(&format!("[{}]", names.join(", ")))
+                    .map(|extensions| overrides.extensions = extensions)
+                    .map_err(|e| e.to_string()),
+                Setting::Options(options) => options
+                    .iter()
+                    .map(|option| parse_option(option))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|options| overrides.pandoc_options = options.concat()),
+            };
+
+            if let Err(e) = result {
+                errors.push(source.error(range, &key, e));
+            }
+        }
+
+        errors.finish()?;
+        Ok(overrides)
+    }
+}
+
+fn unknown_key(source: &Source, range: Option<Range<usize>>, key: &str) -> syn::Error {
+    source.error(range, key, format!("unknown key, expected one of {}", KEYS.join(", ")))
+}
+
+fn read_toml(source: &Source) -> syn::Result<(Vec<Entry>, Errors)> {
+    let document = source.document()?;
+    let strings = |item: &Item, key: &str| {
+        let list: Option<Vec<String>> = item
+            .as_array()
+            .and_then(|list| list.iter().map(|v| v.as_str().map(str::to_string)).collect());
+        list.ok_or_else(|| source.error(item.span(), key, "expected a list of strings"))
+    };
+
+    let mut entries = Vec::new();
+    let mut errors = Errors::default();
+    for (key, item) in document.iter() {
+        let range = document.key(key).and_then(|key| key.span()).or_else(|| item.span());
+        let setting = match key {
+            "input_format" => item
+                .as_value()
+                .ok_or_else(|| source.error(item.span(), key, "expected a string or an inline table"))
+                .and_then(|value: &Value| format_syntax(value, source, key))
+                .map(Setting::InputFormat),
+            "extensions" => strings(item, key).map(Setting::Extensions),
+            "options" => strings(item, key).map(Setting::Options),
+            _ => Err(unknown_key(source, range.clone(), key)),
+        };
+
+        match setting {
+            Ok(setting) => entries.push((key.to_string(), range, setting)),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    Ok((entries, errors))
+}
+
+fn read_yaml(source: &Source) -> syn::Result<(Vec<Entry>, Errors)> {
+    let invalid = |line: usize, message: &str| {
+        syn::Error::new(source.span, format!("{}:{}: {}", source.path, line, message))
+    };
+    let documents = YamlLoader::load_from_str(source.text).map_err(|e| invalid(e.marker().line(), e.info()))?;
+    let map = match documents.into_iter().next() {
+        None | Some(Yaml::Null) => return Ok((Vec::new(), Errors::default())),
+        Some(Yaml::Hash(map)) => map,
+        Some(_) => return Err(invalid(1, "expected a mapping of options")),
+    };
+
+    let mut entries = Vec::new();
+    let mut errors = Errors::default();
+    for (key, value) in map {
+        let key = key.into_string().unwrap_or_default();
+        let range = yaml_key(source.text, &key);
+        let strings = |value: &Yaml| {
+            let list: Option<Vec<String>> = value
+                .as_vec()
+                .and_then(|list| list.iter().map(|v| v.as_str().map(str::to_string)).collect());
+            list.ok_or_else(|| source.error(range.clone(), &key, "expected a list of strings"))
+        };
+
+        let setting = match key.as_str() {
+            "input_format" => yaml_format(&value)
+                .ok_or_else(|| source.error(range.clone(), &key, "expected a format name or { format, extensions }"))
+                .map(Setting::InputFormat),
+            "extensions" => strings(&value).map(Setting::Extensions),
+            "options" => strings(&value).map(Setting::Options),
+            _ => Err(unknown_key(source, range.clone(), &key)),
+        };
+
+        match setting {
+            Ok(setting) => entries.push((key, range, setting)),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    Ok((entries, errors))
+}
+
+/// A format as a name in the macro's syntax or a `{ format, extensions }` mapping
+fn yaml_format(value: &Yaml) -> Option<String> {
+    if let Some(format) = value.as_str() {
+        return Some(format.to_string());
+    }
+
+    let map = value.as_hash()?;
+    let mut format = None;
+    let mut extensions = Vec::new();
+    for (key, value) in map {
+        match key.as_str()? {
+            "format" => format = Some(value.as_str()?),
+            "extensions" => {
+                extensions = value.as_vec()?.iter().map(Yaml::as_str).collect::<Option<_>>()?;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(format!("{}[{}]", format?, extensions.join(", ")))
+}
+
+/// Position of a top-level `key:` in a YAML file
+fn yaml_key(text: &str, key: &str) -> Option<Range<usize>> {
+    text.match_indices(key)
+        .map(|(start, _)| start)
+        .find(|&start| (start == 0 || text[..start].ends_with('\n')) && text[start + key.len()..].starts_with(':'))
+        .map(|start| start..start + key.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str, text: &str) -> syn::Result<Overrides> {
+        let source = Source {
+            path: name,
+            text,
+            span: Span::call_site(),
+        };
+        let entries = if name.ends_with(".toml") { read_toml(&source) } else { read_yaml(&source) };
+        entries.and_then(|(entries, errors)| Overrides::parse(&source, entries, errors))
+    }
+
+    fn inherited() -> SourceOptions {
+        SourceOptions {
+            input_format: Some((InputFormat::Markdown, vec![MarkdownExtension::Smart])),
+            pandoc_options: vec![
+                PandocOption::NumberSections,
+                PandocOption::ShiftHeadingLevelBy(1),
+                PandocOption::Filter("a.py".into()),
+                PandocOption::Meta("lang".into(), Some("en".into())),
+            ],
+        }
+    }
+
+    #[test]
+    fn toml_and_yaml_read_the_same_settings() {
+        let toml = parse(
+            "__options__.toml",
+            "input_format = { format = \"Commonmark\" }\nextensions = [\"Footnotes\"]\noptions = [\"TableOfContents\"]\n",
+        )
+        .unwrap();
+        let yaml = parse(
+            "_dir.yaml",
+            "input_format:\n  format: Commonmark\nextensions: [Footnotes]\noptions:\n  - TableOfContents\n",
+        )
+        .unwrap();
+
+        for overrides in [toml, yaml] {
+            assert_eq!(overrides.input_format.unwrap().0.to_string(), "commonmark");
+            assert_eq!(overrides.extensions.len(), 1);
+            assert!(matches!(overrides.pandoc_options.as_slice(), [PandocOption::TableOfContents]));
+        }
+        assert!(parse("_dir.yaml", "").unwrap().pandoc_options.is_empty());
+    }
+
+    #[test]
+    fn options_replace_their_kind_and_repeatable_ones_add_up() {
+        let overrides = parse(
+            "__options__.toml",
+            r#"options = ["ShiftHeadingLevelBy { 2 }", 'Filter { "b.py" }', 'Meta { "lang", "de" }', 'Meta { "title", "Guide" }']"#,
+        )
+        .unwrap();
+        let merged = inherited().merge(overrides);
+
+        let options: Vec<String> = merged.pandoc_options.iter().map(|o| format!("{:?}", o)).collect();
+        assert_eq!(
+            options,
+            [
+                "NumberSections",
+                "Filter(\"a.py\")",
+                "ShiftHeadingLevelBy(2)",
+                "Filter(\"b.py\")",
+                "Meta(\"lang\", Some(\"de\"))",
+                "Meta(\"title\", Some(\"Guide\"))",
+            ]
+        );
+    }
+
+    #[test]
+    fn extensions_add_to_the_inherited_format() {
+        let merged = inherited().merge(parse("_dir.yaml", "extensions: [Smart, Footnotes]").unwrap());
+        let (format, extensions) = merged.input_format.unwrap();
+        assert_eq!(format.to_string(), "markdown");
+        assert_eq!(extensions.len(), 2);
+
+        let merged = inherited().merge(parse("_dir.yaml", "input_format: Commonmark").unwrap());
+        let (format, extensions) = merged.input_format.unwrap();
+        assert_eq!(format.to_string(), "commonmark");
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    fn relative_files_resolve_against_the_options_file() {
+        let dir = std::env::temp_dir().join(format!("pandoc-generator-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("local.lua"), "").unwrap();
+        fs::write(
+            dir.join("_dir.yaml"),
+            "options:\n  - 'Template { \"page.html\" }'\n  - 'Csl { \"/styles/apa.csl\" }'\n  - 'LuaFilter { \"local.lua\" }'\n  - 'Filter { \"pandoc-crossref\" }'\n",
+        )
+        .unwrap();
+
+        let (options, _) = SourceOptions::default().enter(&dir).unwrap();
+        let options: Vec<String> = options.pandoc_options.iter().map(|o| format!("{:?}", o)).collect();
+        assert_eq!(
+            options,
+            [
+                format!("Template({:?})", dir.join("page.html")),
+                "Csl(\"/styles/apa.csl\")".to_string(),
+                format!("LuaFilter({:?})", dir.join("local.lua")),
+                "Filter(\"pandoc-crossref\")".to_string(),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn errors_report_file_line_and_key() {
+        let err = parse("_dir.yaml", "extensions: [Smart]\noptions: [NoSuchOption]\ncolor: red\n").err().unwrap();
+        let messages: Vec<String> = err.into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0],
+            "_dir.yaml:3: `color`: unknown key, expected one of input_format, extensions, options"
+        );
+        assert!(messages[1].starts_with("_dir.yaml:2: `options`: "), "{}", messages[1]);
+
+        let err = parse("__options__.toml", "\nextensions = \"Smart\"").err().unwrap();
+        assert_eq!(err.to_string(), "__options__.toml:2: `extensions`: expected a list of strings");
+
+        let err = parse("_dir.yaml", "options: [a\n").err().unwrap();
+        assert!(err.to_string().starts_with("_dir.yaml:"), "{}", err);
+    }
+}
@@ -0,0 +1,238 @@
+use syn::{Ident, LitInt, LitStr, Token, braced, bracketed, parse::Parse, punctuated::Punctuated};
+
+/// `feed = { title: "Blog", from: ["content/blog"], limit: 10, author: "Jane" }`
+pub(crate) struct FeedOptions {
+    pub title: String,
+    /// `compile_from_path` paths whose pages go into the feed, all if empty
+    pub from: Vec<LitStr>,
+    pub limit: usize,
+    pub author: Option<String>,
+}
+
+impl Parse for FeedOptions {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let content;
+        let brace = braced!(content in input);
+
+        let mut title = None;
+        let mut from = None;
+        let mut limit = None;
+        let mut author = None;
+
+        while !content.is_empty() {
+            let key = content.parse::<Ident>()?;
+            let _ = content.parse::<Token![:]>()?;
+
+            let duplicate = match key.to_string().as_str() {
+                "title" => title.replace(content.parse::<LitStr>()?.value()).is_some(),
+                "author" => author.replace(content.parse::<LitStr>()?.value()).is_some(),
+                "limit" => limit.replace(content.parse::<LitInt>()?.base10_parse::<usize>()?).is_some(),
+                "from" => {
+                    let list;
+                    bracketed!(list in content);
+                    let paths = Punctuated::<LitStr, Token![,]>::parse_terminated(&list)?;
+                    from.replace(paths.into_iter().collect::<Vec<_>>()).is_some()
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown feed option `{}`, expected title, from, limit or author", other),
+                    ));
+                }
+            };
+
+            if duplicate {
+                return Err(syn::Error::new(key.span(), format!("feed {} specified multiple times", key)));
+            }
+
+            if !content.is_empty() {
+                let _ = content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self {
+            title: title.ok_or_else(|| syn::Error::new(brace.span.join(), "feed title is required"))?,
+            from: from.unwrap_or_default(),
+            limit: limit.unwrap_or(20),
+            author,
+        })
+    }
+}
+
+/// A routed page as seen by the sitemap and the feed
+pub(crate) struct SitePage {
+    pub route: String,
+    pub title: String,
+    pub date: Option<(i32, u8, u8)>,
+    pub summary: String,
+    /// Position of the content element the page comes from
+    pub element: usize,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn url(base_url: &str, route: &str) -> String {
+    format!("{}{}", base_url.trim_end_matches('/'), route)
+}
+
+fn timestamp((year, month, day): (i32, u8, u8)) -> String {
+    format!("{:04}-{:02}-{:02}T00:00:00Z", year, month, day)
+}
+
+/// `sitemap.xml` listing every page, with its date as `lastmod`
+pub(crate) fn sitemap_xml(base_url: &str, pages: &[SitePage]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for page in pages {
+        out.push_str(&format!("  <url>\n    <loc>{}</loc>\n", escape(&url(base_url, &page.route))));
+        if let Some((year, month, day)) = page.date {
+            out.push_str(&format!("    <lastmod>{:04}-{:02}-{:02}</lastmod>\n", year, month, day));
+        }
+        out.push_str("  </url>\n");
+    }
+
+    out.push_str("</urlset>\n");
+    out
+}
+
+/// Atom feed of the newest dated pages, newest first
+pub(crate) fn atom_feed(base_url: &str, feed: &FeedOptions, pages: &[SitePage]) -> String {
+    let mut entries: Vec<(&SitePage, (i32, u8, u8))> = pages
+        .iter()
+        .filter_map(|page| Some((page, page.date?)))
+        .collect();
+    // Stable sort keeps reading order among pages of the same day
+    entries.sort_by_key(|(_, date)| std::cmp::Reverse(*date));
+    entries.truncate(feed.limit);
+
+    let home = url(base_url, "/");
+    let updated = entries.first().map_or((1970, 1, 1), |(_, date)| *date);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("  <title>{}</title>\n", escape(&feed.title)));
+    out.push_str(&format!("  <id>{}</id>\n", escape(&home)));
+    out.push_str(&format!("  <link href=\"{}\"/>\n", escape(&home)));
+    out.push_str(&format!("  <updated>{}</updated>\n", timestamp(updated)));
+    if let Some(author) = &feed.author {
+        out.push_str(&format!("  <author>\n    <name>{}</name>\n  </author>\n", escape(author)));
+    }
+
+    for (page, date) in entries {
+        let link = escape(&url(base_url, &page.route));
+        out.push_str("  <entry>\n");
+        out.push_str(&format!("    <title>{}</title>\n", escape(&page.title)));
+        out.push_str(&format!("    <id>{}</id>\n", link));
+        out.push_str(&format!("    <link href=\"{}\"/>\n", link));
+        out.push_str(&format!("    <updated>{}</updated>\n", timestamp(date)));
+        if !page.summary.is_empty() {
+            out.push_str(&format!("    <summary>{}</summary>\n", escape(&page.summary)));
+        }
+        out.push_str("  </entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+/// First `max` characters of `text`, cut at a word boundary
+pub(crate) fn summarize(text: &str, max: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max {
+        return text;
+    }
+
+    let cut: String = text.chars().take(max).collect();
+    match cut.rfind(' ') {
+        Some(i) => format!("{}…", &cut[..i]),
+        None => format!("{}…", cut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(route: &str, title: &str, date: Option<(i32, u8, u8)>) -> SitePage {
+        SitePage {
+            route: route.to_string(),
+            title: title.to_string(),
+            date,
+            summary: String::new(),
+            element: 0,
+        }
+    }
+
+    #[test]
+    fn feed_options_are_parsed() {
+        let feed: FeedOptions =
+            syn::parse_str(r#"{ title: "Blog", from: ["content/blog"], limit: 5 }"#).unwrap();
+        assert_eq!(feed.title, "Blog");
+        assert_eq!(feed.from.len(), 1);
+        assert_eq!(feed.limit, 5);
+        assert_eq!(feed.author, None);
+
+        let feed: FeedOptions = syn::parse_str(r#"{ title: "Blog" }"#).unwrap();
+        assert!(feed.from.is_empty());
+        assert_eq!(feed.limit, 20);
+    }
+
+    #[test]
+    fn feed_options_are_checked() {
+        assert!(syn::parse_str::<FeedOptions>(r#"{ limit: 5 }"#).is_err());
+        assert!(syn::parse_str::<FeedOptions>(r#"{ title: "a", title: "b" }"#).is_err());
+
+        let err = syn::parse_str::<FeedOptions>(r#"{ title: "a", size: 5 }"#).err().unwrap();
+        assert!(err.to_string().contains("unknown feed option `size`"));
+    }
+
+    #[test]
+    fn sitemap_lists_pages_with_lastmod() {
+        let xml = sitemap_xml(
+            "https://example.com/",
+            &[page("/a&b", "A", Some((2024, 5, 1))), page("/c", "C", None)],
+        );
+        assert!(xml.contains("<loc>https://example.com/a&amp;b</loc>\n    <lastmod>2024-05-01</lastmod>"));
+        assert!(xml.contains("<loc>https://example.com/c</loc>\n  </url>"));
+    }
+
+    #[test]
+    fn feed_has_newest_dated_entries_first() {
+        let feed = FeedOptions {
+            title: "Blog".to_string(),
+            from: Vec::new(),
+            limit: 2,
+            author: Some("Jane".to_string()),
+        };
+        let xml = atom_feed(
+            "https://example.com",
+            &feed,
+            &[
+                page("/old", "Old", Some((2023, 1, 1))),
+                page("/undated", "Undated", None),
+                page("/new", "New", Some((2024, 2, 3))),
+                page("/mid", "Mid", Some((2023, 6, 1))),
+            ],
+        );
+
+        assert!(xml.contains("<updated>2024-02-03T00:00:00Z</updated>\n  <author>"));
+        let new = xml.find("<title>New</title>").unwrap();
+        let mid = xml.find("<title>Mid</title>").unwrap();
+        assert!(new < mid);
+        assert!(!xml.contains("Old"));
+        assert!(!xml.contains("Undated"));
+    }
+
+    #[test]
+    fn summaries_are_cut_at_words() {
+        assert_eq!(summarize("short  text", 20), "short text");
+        assert_eq!(summarize("one two three", 9), "one two…");
+    }
+}
@@ -1,39 +1,79 @@
-use std::{fs, io, path::PathBuf};
-
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    assets::is_static,
+    sidecar::{OPTIONS_FILES, SourceOptions},
+};
+
+/// A source file or directory, in conversion order. Sources carry the
+/// options they are converted with.
 #[derive(Debug)]
-pub(crate) enum TreeElement {
-    File(PathBuf),
-    ModFile(PathBuf),
+pub enum TreeElement {
+    File(PathBuf, SourceOptions),
+    ModFile(PathBuf, SourceOptions),
     Nested(PathBuf, Vec<TreeElement>),
 }
 
+/// The sources found under one content path
 #[derive(Debug)]
-pub(crate) struct FsTree {
+pub struct FsTree {
     pub tree: TreeElement,
     pub route: Option<String>,
+    /// Every directory read while building the tree, including pruned ones
+    pub dirs: Vec<PathBuf>,
+    /// Files embedded as static assets instead of being converted
+    pub assets: Vec<PathBuf>,
+    /// Per-directory options files applied to the sources
+    pub options_files: Vec<PathBuf>,
+}
+
+/// Paths gathered while walking the content directory
+#[derive(Default)]
+struct Visited {
+    dirs: Vec<PathBuf>,
+    assets: Vec<PathBuf>,
+    options_files: Vec<PathBuf>,
 }
 
 impl FsTree {
+    /// Explore `rootdir`, converting its sources with `options` as changed
+    /// by the options files of the directories they are in
     pub(crate) fn construct(
         rootdir: PathBuf,
         route: &Option<String>,
         mod_file_name: &str,
         source_ext: &Option<String>,
+        asset_exts: &[String],
+        options: &SourceOptions,
     ) -> Result<Self, io::Error> {
         if rootdir.is_file() {
+            // A single file still gets the options file next to it
+            let (options, file) = options.enter(rootdir.parent().unwrap_or(Path::new("")))?;
             Ok(Self {
-                tree: TreeElement::File(rootdir),
+                tree: TreeElement::File(rootdir, options),
                 route: route.clone(),
+                dirs: Vec::new(),
+                assets: Vec::new(),
+                options_files: file.into_iter().collect(),
             })
         } else {
             if rootdir.join(mod_file_name).is_file() {
+                let (options, file) = options.enter(&rootdir)?;
                 return Ok(Self {
-                    tree: TreeElement::ModFile(rootdir.join(mod_file_name)),
-                    route: route.clone()
+                    tree: TreeElement::ModFile(rootdir.join(mod_file_name), options),
+                    route: route.clone(),
+                    dirs: vec![rootdir],
+                    assets: Vec::new(),
+                    options_files: file.into_iter().collect(),
                 });
             }
 
-            let components = FsTree::make_tree(&rootdir, mod_file_name, source_ext)?;
+            let mut visited = Visited::default();
+            let components =
+                FsTree::make_tree(&rootdir, mod_file_name, source_ext, asset_exts, options, &mut visited)?;
 
             if components.is_empty() {
                 Err(io::Error::new(
@@ -51,11 +91,13 @@ impl FsTree {
                 Ok(Self {
                     tree: TreeElement::Nested(rootdir, components),
                     route: route.clone(),
+                    dirs: visited.dirs,
+                    assets: visited.assets,
+                    options_files: visited.options_files,
                 })
             }
         }
     }
-
     fn parse_order(path_str: &str) -> u32 {
         // Extract the first component (directory/file name)
         let name = path_str;
@@ -78,8 +120,18 @@ impl FsTree {
         u32::MAX
     }
 
-    fn make_tree(dir: &PathBuf, mod_file_name: &str, source_ext: &Option<String>) -> Result<Vec<TreeElement>, io::Error> {
+    fn make_tree(
+        dir: &PathBuf,
+        mod_file_name: &str,
+        source_ext: &Option<String>,
+        asset_exts: &[String],
+        inherited: &SourceOptions,
+        visited: &mut Visited,
+    ) -> Result<Vec<TreeElement>, io::Error> {
         let mut components = Vec::new();
+        visited.dirs.push(dir.clone());
+        let (options, file) = inherited.enter(dir)?;
+        visited.options_files.extend(file);
 
         for entry in fs::read_dir(dir)? {
             let path = entry?.path();
@@ -88,35 +140,50 @@ impl FsTree {
                 // Check for __mod__.<ext> file inside this directory
                 let mod_path = path.join(mod_file_name);
                 if mod_path.is_file() {
-                    components.push(TreeElement::ModFile(mod_path));
+                    let (options, file) = options.enter(&path)?;
+                    components.push(TreeElement::ModFile(mod_path, options));
+                    visited.options_files.extend(file);
+                    visited.dirs.push(path);
                     continue;
                 }
 
-                let subtree = FsTree::make_tree(&path, mod_file_name, source_ext)?;
+                let subtree = FsTree::make_tree(&path, mod_file_name, source_ext, asset_exts, &options, visited)?;
                 if !subtree.is_empty() {
                     components.push(TreeElement::Nested(path, subtree));
                 }
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| OPTIONS_FILES.contains(&name))
+            {
+                continue;
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| asset_exts.iter().any(|a| a == e))
+            {
+                visited.assets.push(path);
             } else {
                 // Only include files matching the source extension (if specified)
                 if let Some(ext) = source_ext {
                     if path.extension().and_then(|e| e.to_str()) == Some(ext.as_str()) {
-                        components.push(TreeElement::File(path));
+                        components.push(TreeElement::File(path, options.clone()));
                     }
-                } else {
-                    components.push(TreeElement::File(path));
+                } else if !is_static(&path) {
+                    components.push(TreeElement::File(path, options.clone()));
                 }
             }
         }
 
         components.sort_by_key(|i| {
             let path = match i {
-                TreeElement::File(path) => path,
-                TreeElement::ModFile(path) => path,
+                TreeElement::File(path, _) => path,
+                TreeElement::ModFile(path, _) => path,
                 TreeElement::Nested(path, _) => path,
             };
 
-            let index = path.iter().last().unwrap().to_str().unwrap();
-            let order = Self::parse_order(&index);
+            let index = path.iter().next_back().unwrap().to_str().unwrap();
+            let order = Self::parse_order(index);
 
             (order, index.to_owned())
         });
@@ -124,29 +191,30 @@ impl FsTree {
         Ok(components)
     }
 
-    fn list_files(components: &[TreeElement], list: &mut Vec<PathBuf>) {
-        for el in components.iter() {
-            match el {
-                TreeElement::File(path) | TreeElement::ModFile(path) => {
-                    list.push(path.clone());
+    fn list_sources<'a>(element: &'a TreeElement, list: &mut Vec<(&'a PathBuf, &'a SourceOptions)>) {
+        match element {
+            TreeElement::File(path, options) | TreeElement::ModFile(path, options) => {
+                list.push((path, options));
+            }
+            TreeElement::Nested(_, subtree) => {
+                for el in subtree.iter() {
+                    Self::list_sources(el, list);
                 }
-                TreeElement::Nested(_, subtree) => Self::list_files(subtree, list),
             }
         }
     }
 
-    pub(crate) fn get_all_src_files(&self) -> Vec<PathBuf> {
+    /// Every source file with its options, in tree order
+    pub fn sources(&self) -> Vec<(&PathBuf, &SourceOptions)> {
         let mut list = Vec::new();
-
-        match &self.tree {
-            TreeElement::File(path) | TreeElement::ModFile(path) => {
-                list.push(path.clone())
-            }
-            TreeElement::Nested(_, components) => Self::list_files(components, &mut list),
-        }
-
+        Self::list_sources(&self.tree, &mut list);
         list
     }
+
+    /// Every source file, in tree order
+    pub fn get_all_src_files(&self) -> Vec<PathBuf> {
+        self.sources().into_iter().map(|(path, _)| path.clone()).collect()
+    }
 }
 
 #[cfg(test)]
@@ -203,8 +271,8 @@ mod tests {
         let file_path = tmp.path().join("hello.md");
         write_file(tmp.path(), "hello.md", "content");
 
-        let tree = FsTree::construct(file_path.clone(), &None, "__mod__.md", &None).unwrap();
-        assert!(matches!(tree.tree, TreeElement::File(ref p) if p == &file_path));
+        let tree = FsTree::construct(file_path.clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
+        assert!(matches!(tree.tree, TreeElement::File(ref p, _) if p == &file_path));
         assert!(tree.route.is_none());
     }
 
@@ -214,7 +282,7 @@ mod tests {
         write_file(tmp.path(), "a.md", "a");
         write_file(tmp.path(), "b.md", "b");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => assert_eq!(components.len(), 2),
             other => panic!("expected Nested, got variant at {:?}", name_of(other)),
@@ -227,7 +295,7 @@ mod tests {
         write_file(tmp.path(), "x.md", "x");
 
         let route = Some("/myroute".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &route, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &route, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         assert_eq!(tree.route, route);
     }
 
@@ -238,7 +306,7 @@ mod tests {
         let tmp = TempDir::new("tree_empty_root");
         // No files at all
 
-        let result = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None);
+        let result = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default());
         assert!(result.is_err());
         assert_eq!(result.err().unwrap().kind(), io::ErrorKind::NotFound);
     }
@@ -250,12 +318,12 @@ mod tests {
         let _empty_sub = create_dir(tmp.path(), "empty_sub");
         // empty_sub has no files
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 // Only the root.md file, no Nested for empty_sub
                 assert_eq!(components.len(), 1);
-                assert!(matches!(&components[0], TreeElement::File(p) if p.file_name().unwrap() == "root.md"));
+                assert!(matches!(&components[0], TreeElement::File(p, _) if p.file_name().unwrap() == "root.md"));
             }
             other => panic!("expected Nested, got {:?}", name_of(other)),
         }
@@ -269,7 +337,7 @@ mod tests {
         let _sub2 = create_dir(&sub1, "sub2");
         // sub1/sub2 are both empty
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 assert_eq!(components.len(), 1); // only top.md
@@ -280,6 +348,38 @@ mod tests {
 
     // ── source_ext filtering ────────────────────────────────────────
 
+    #[test]
+    fn asset_extensions_are_not_sources() {
+        let tmp = TempDir::new("tree_assets");
+        write_file(tmp.path(), "a.md", "a");
+        write_file(tmp.path(), "logo.svg", "<svg/>");
+        let sub = create_dir(tmp.path(), "img");
+        write_file(&sub, "photo.png", "png");
+
+        let assets = ["svg".to_string(), "png".to_string()];
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &assets, &SourceOptions::default()).unwrap();
+        assert_eq!(tree.get_all_src_files(), vec![tmp.path().join("a.md")]);
+
+        let mut found = tree.assets.clone();
+        found.sort();
+        assert_eq!(found, vec![sub.join("photo.png"), tmp.path().join("logo.svg")]);
+    }
+
+    #[test]
+    fn static_files_are_not_sources_without_assets() {
+        let tmp = TempDir::new("tree_static");
+        write_file(tmp.path(), "a.md", "a");
+        write_file(tmp.path(), "b.html", "<p>b</p>");
+        write_file(tmp.path(), "logo.SVG", "<svg/>");
+        write_file(tmp.path(), "style.css", "p {}");
+
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
+        let mut sources = tree.get_all_src_files();
+        sources.sort();
+        assert_eq!(sources, vec![tmp.path().join("a.md"), tmp.path().join("b.html")]);
+        assert!(tree.assets.is_empty());
+    }
+
     #[test]
     fn source_ext_filters_files() {
         let tmp = TempDir::new("tree_ext_filter");
@@ -288,13 +388,13 @@ mod tests {
         write_file(tmp.path(), "c.md", "c");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 assert_eq!(components.len(), 2);
                 for c in components {
                     match c {
-                        TreeElement::File(p) => {
+                        TreeElement::File(p, _) => {
                             assert_eq!(p.extension().unwrap(), "md");
                         }
                         other => panic!("expected File, got {:?}", name_of(other)),
@@ -312,7 +412,7 @@ mod tests {
         write_file(tmp.path(), "b.rs", "b");
 
         let ext = Some("md".to_string());
-        let result = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext);
+        let result = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default());
         assert!(result.is_err());
     }
 
@@ -324,7 +424,7 @@ mod tests {
         write_file(&sub, "bad.txt", "b");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 assert_eq!(components.len(), 1); // only sub directory
@@ -347,7 +447,7 @@ mod tests {
         write_file(&sub, "ignore.txt", "i");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 assert_eq!(components.len(), 1); // only root.md, sub is pruned
@@ -365,12 +465,12 @@ mod tests {
         write_file(&sub, "__mod__.md", "mod content");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 assert_eq!(components.len(), 1);
                 match &components[0] {
-                    TreeElement::ModFile(p) => {
+                    TreeElement::ModFile(p, _) => {
                         assert_eq!(p.file_name().unwrap(), "__mod__.md");
                         assert_eq!(p.parent().unwrap().file_name().unwrap(), "01-mysection");
                     }
@@ -389,12 +489,12 @@ mod tests {
         write_file(&sub, "extra.md", "extra");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 // ModFile takes priority; directory is NOT recursed
                 assert_eq!(components.len(), 1);
-                assert!(matches!(&components[0], TreeElement::ModFile(_)));
+                assert!(matches!(&components[0], TreeElement::ModFile(..)));
             }
             other => panic!("expected Nested, got {:?}", name_of(other)),
         }
@@ -407,9 +507,9 @@ mod tests {
         write_file(tmp.path(), "normal.md", "n");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
-            TreeElement::ModFile(p) => assert_eq!(p, &tmp.path().join("__mod__.md")),
+            TreeElement::ModFile(p, _) => assert_eq!(p, &tmp.path().join("__mod__.md")),
             x => panic!("Expected ModFile got: {:?}", x)
         }
     }
@@ -421,12 +521,12 @@ mod tests {
         write_file(&sub, "__mod__.md", "mod");
 
         // Even with source_ext = None, __mod__.md should be recognized as ModFile
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 // Only the ModFile entry, directory is not recursed
                 assert_eq!(components.len(), 1);
-                assert!(matches!(&components[0], TreeElement::ModFile(p) if p.file_name().unwrap() == "__mod__.md"));
+                assert!(matches!(&components[0], TreeElement::ModFile(p, _) if p.file_name().unwrap() == "__mod__.md"));
             }
             other => panic!("expected Nested, got {:?}", name_of(other)),
         }
@@ -440,7 +540,7 @@ mod tests {
         let file_path = tmp.path().join("single.md");
         write_file(tmp.path(), "single.md", "s");
 
-        let tree = FsTree::construct(file_path.clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(file_path.clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         let srcs = tree.get_all_src_files();
         assert_eq!(srcs, vec![file_path]);
     }
@@ -453,7 +553,7 @@ mod tests {
         write_file(tmp.path(), "root.md", "r");
 
         let ext = Some("md".to_string());
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &ext, &[], &SourceOptions::default()).unwrap();
         let srcs = tree.get_all_src_files();
         assert_eq!(srcs.len(), 2);
 
@@ -473,11 +573,35 @@ mod tests {
         let deep = create_dir(&sub, "deep");
         write_file(&deep, "bottom.md", "b");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         let srcs = tree.get_all_src_files();
         assert_eq!(srcs.len(), 3);
     }
 
+    #[test]
+    fn scanned_dirs_include_pruned_and_mod_dirs() {
+        let tmp = TempDir::new("tree_dirs");
+        write_file(tmp.path(), "top.md", "t");
+        let empty = create_dir(tmp.path(), "empty");
+        let module = create_dir(tmp.path(), "module");
+        write_file(&module, "__mod__.md", "m");
+
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
+        assert_eq!(tree.dirs.len(), 3);
+        assert!(tree.dirs.contains(tmp.path()));
+        assert!(tree.dirs.contains(&empty));
+        assert!(tree.dirs.contains(&module));
+    }
+
+    #[test]
+    fn single_file_tree_scans_no_dirs() {
+        let tmp = TempDir::new("tree_dirs_file");
+        write_file(tmp.path(), "single.md", "s");
+
+        let tree = FsTree::construct(tmp.path().join("single.md"), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
+        assert!(tree.dirs.is_empty());
+    }
+
     // ── Sorting ─────────────────────────────────────────────────────
 
     #[test]
@@ -487,11 +611,11 @@ mod tests {
         write_file(tmp.path(), "a.md", "a");
         write_file(tmp.path(), "m.md", "m");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 let names: Vec<&str> = components.iter().map(|c| match c {
-                    TreeElement::File(p) => p.file_name().unwrap().to_str().unwrap(),
+                    TreeElement::File(p, _) => p.file_name().unwrap().to_str().unwrap(),
                     other => panic!("expected File, got {:?}", name_of(other)),
                 }).collect();
                 assert_eq!(names, vec!["a.md", "m.md", "z.md"]);
@@ -511,12 +635,12 @@ mod tests {
         let sub4 = create_dir(tmp.path(), "03-third");
         write_file(&sub4, "content.md", "c3");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 let names: Vec<&str> = components.iter().map(|c| match c {
                     TreeElement::Nested(p, _) => p.file_name().unwrap().to_str().unwrap(),
-                    TreeElement::File(p) => p.file_name().unwrap().to_str().unwrap(),
+                    TreeElement::File(p, _) => p.file_name().unwrap().to_str().unwrap(),
                     other => panic!("expected Nested/File, got {:?}", name_of(other)),
                 }).collect();
                 // Should be ordered by numeric prefix: 01-first, 02-second, 03-third, z.md
@@ -536,7 +660,7 @@ mod tests {
         let sub_01 = create_dir(tmp.path(), "01-with-num");
         write_file(&sub_01, "c.md", "c");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 let names: Vec<&str> = components.iter().map(|c| match c {
@@ -563,12 +687,12 @@ mod tests {
         let sub_no_num = create_dir(tmp.path(), "unordered");
         write_file(&sub_no_num, "x.md", "u");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 let names: Vec<&str> = components.iter().map(|c| match c {
                     TreeElement::Nested(p, _) => p.file_name().unwrap().to_str().unwrap(),
-                    TreeElement::File(p) => p.file_name().unwrap().to_str().unwrap(),
+                    TreeElement::File(p, _) => p.file_name().unwrap().to_str().unwrap(),
                     other => panic!("expected Nested/File, got {:?}", name_of(other)),
                 }).collect();
                 // Should sort by numeric prefix regardless of gaps: 1-c, 2-a, 3-b, unordered
@@ -595,7 +719,7 @@ mod tests {
         let sub_10 = create_dir(tmp.path(), "10-larger-number");
         write_file(&sub_10, "x.md", "d");
 
-        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None).unwrap();
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default()).unwrap();
         match &tree.tree {
             TreeElement::Nested(_, components) => {
                 let names: Vec<&str> = components.iter().map(|c| match c {
@@ -609,12 +733,91 @@ mod tests {
         }
     }
 
+    // ── Options files ───────────────────────────────────────────────
+
+    #[test]
+    fn options_files_apply_to_their_directory_and_below() {
+        let tmp = TempDir::new("tree_options_files");
+        write_file(tmp.path(), "__options__.toml", "options = [\"NumberSections\"]");
+        write_file(tmp.path(), "top.md", "top");
+        let guide = create_dir(tmp.path(), "guide");
+        write_file(&guide, "_dir.yaml", "input_format: Commonmark\noptions: [TableOfContents]");
+        write_file(&guide, "setup.md", "setup");
+        let module = create_dir(&guide, "module");
+        write_file(&module, "__mod__.md", "module");
+
+        let inherited = SourceOptions {
+            input_format: Some((pandoc::InputFormat::Markdown, Vec::new())),
+            pandoc_options: vec![pandoc::PandocOption::Standalone],
+        };
+        let tree = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &inherited).unwrap();
+
+        let sources: Vec<(String, String, usize)> = tree
+            .sources()
+            .into_iter()
+            .map(|(path, options)| {
+                let format = options.input_format.as_ref().unwrap().0.to_string();
+                (path.file_name().unwrap().to_str().unwrap().to_string(), format, options.pandoc_options.len())
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                ("__mod__.md".to_string(), "commonmark".to_string(), 3),
+                ("setup.md".to_string(), "commonmark".to_string(), 3),
+                ("top.md".to_string(), "markdown".to_string(), 2),
+            ]
+        );
+        assert_eq!(tree.options_files.len(), 2);
+    }
+
+    #[test]
+    fn single_files_use_the_options_file_of_their_directory() {
+        let tmp = TempDir::new("tree_single_file_options");
+        write_file(tmp.path(), "_dir.yaml", "input_format: Commonmark");
+        write_file(tmp.path(), "page.md", "page");
+
+        let tree = FsTree::construct(tmp.path().join("page.md"), &None, "__mod__.md", &None, &[], &SourceOptions::default())
+            .unwrap();
+        match &tree.tree {
+            TreeElement::File(_, options) => {
+                assert_eq!(options.input_format.as_ref().unwrap().0.to_string(), "commonmark");
+            }
+            other => panic!("expected File, got {:?}", name_of(other)),
+        }
+        assert_eq!(tree.options_files, [tmp.path().join("_dir.yaml")]);
+    }
+
+    #[test]
+    fn two_options_files_in_one_directory_are_rejected() {
+        let tmp = TempDir::new("tree_two_options_files");
+        write_file(tmp.path(), "__options__.toml", "");
+        write_file(tmp.path(), "_dir.yaml", "");
+        write_file(tmp.path(), "a.md", "a");
+
+        let err = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("has both __options__.toml and _dir.yaml"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_options_files_name_the_file_and_key() {
+        let tmp = TempDir::new("tree_invalid_options_file");
+        let sub = create_dir(tmp.path(), "sub");
+        write_file(&sub, "__options__.toml", "\noptions = \"Standalone\"");
+        write_file(&sub, "a.md", "a");
+
+        let err = FsTree::construct(tmp.path().clone(), &None, "__mod__.md", &None, &[], &SourceOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().ends_with("__options__.toml:2: `options`: expected a list of strings"), "{}", err);
+    }
+
     // ── Helpers ─────────────────────────────────────────────────────
 
     fn name_of(elem: &TreeElement) -> &'static str {
         match elem {
-            TreeElement::File(_) => "File",
-            TreeElement::ModFile(_) => "ModFile",
+            TreeElement::File(..) => "File",
+            TreeElement::ModFile(..) => "ModFile",
             TreeElement::Nested(_, _) => "Nested",
         }
     }
@@ -1,86 +1,74 @@
 #![crate_type = "proc-macro"]
+#![cfg_attr(feature = "nightly", feature(proc_macro_tracked_path))]
 extern crate proc_macro;
 
-use std::{collections::HashMap, path::PathBuf, sync::mpsc::channel};
-
-use generator::generate_content_tree;
-use options::{Element, Options};
-use pandoc::Pandoc;
+use pandoc_generator_core::expand_macro;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
-use threadpool::ThreadPool;
-use tqdm::tqdm;
-use tree::FsTree;
-
-mod arg;
-mod generator;
-mod options;
-mod tree;
-
-const MOD_FILE_PREFIX: &str = "__mod__";
 
+/// Convert a content tree into a module at compile time, see the README
+/// for every argument.
+///
+/// `check_links = deny` turns a broken local link into a compile error:
+///
+/// ```compile_fail
+/// pandoc_generator::pandoc_compile_html! {
+///     mod_name = docs,
+///     tree_name = DOCS,
+///     content = [ compile_from_path(path: "tests/assets/broken_links", route: "/") ],
+///     check_links = deny,
+///     backend = Mock,
+///     cache = false
+/// }
+/// # fn main() {}
+/// ```
+///
+/// while `warn` reports it as a deprecation warning and still compiles:
+///
+/// ```
+/// pandoc_generator::pandoc_compile_html! {
+///     mod_name = docs,
+///     tree_name = DOCS,
+///     content = [ compile_from_path(path: "tests/assets/broken_links", route: "/") ],
+///     check_links = warn,
+///     backend = Mock,
+///     cache = false
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+/// pandoc_generator::pandoc_compile_html! {
+///     mod_name = docs,
+///     tree_name = DOCS,
+///     content = [ compile_from_path(path: "tests/assets/broken_links", route: "/") ],
+///     check_links = warn,
+///     backend = Mock,
+///     cache = false
+/// }
+/// # fn main() {}
+/// ```
 #[proc_macro]
 pub fn pandoc_compile_html(items: TokenStream) -> TokenStream {
-    let options: Options = parse_macro_input!(items);
-
-    let pool = ThreadPool::new(options.nproc);
-
-    let mut trees = Vec::new();
-    let mut srcs = Vec::new();
-    let mod_file_name = options.source_ext.as_ref()
-        .map_or(MOD_FILE_PREFIX.to_string(), |i| format!("{}.{}", MOD_FILE_PREFIX, i));
-
-    for element in options.content.iter() {
-        if let Element::CompileFromPath { path, route } = element {
-            let tree = FsTree::construct(PathBuf::from(path), route, &mod_file_name, &options.source_ext)
-                .expect(format!("Failed to explore dir {}", path).as_str());
-            srcs.extend(tree.get_all_src_files().into_iter());
-            trees.push(tree);
+    let out = match expand_macro(items.into()) {
+        Ok(generated) => {
+            #[cfg(feature = "nightly")]
+            track_paths(&generated);
+            generated.into_tokens()
         }
-    }
-
-    let mut out = HashMap::new();
-
-    println!("Starting pandoc");
-    for src in tqdm(srcs.iter()) {
-        let (tx, rx) = channel();
-        let src_file = src.clone();
-        let pandoc_options = options.pandoc_options.clone();
-        let input_format = options.input_format.clone();
-        let output_format = options.output_format.clone();
-
-        pool.execute(move || {
-            let mut pandoc = Pandoc::new();
-            pandoc.add_options(&pandoc_options);
-
-            if let Some((fmt, exts)) = input_format {
-                pandoc.set_input_format(fmt, exts);
-            }
+        Err(e) => e.to_compile_error(),
+    };
 
-            if let Some((fmt, exts)) = output_format {
-                pandoc.set_output_format(fmt, exts);
-            }
-
-            pandoc.set_input(pandoc::InputKind::Files(vec![src_file]));
-            pandoc.set_output(pandoc::OutputKind::Pipe);
-            tx.send(pandoc.execute()).unwrap();
-        });
-
-        out.insert(src, rx);
-    }
-
-    let mut outputs = HashMap::new();
-    println!("Gathering results");
-    for (path, rx) in tqdm(out.iter_mut()) {
-        let output = rx
-            .recv()
-            .expect("Failed to read result from pandoc")
-            .expect(format!("Pandoc failed to convert the file {:?}", path).as_str());
-
-        outputs.insert(path, output);
-    }
-
-    let out = generate_content_tree(&options, &trees, &outputs).into();
+    let out = out.into();
     println!("{}", out);
     out
 }
+
+/// Register scanned directories and sources with the compiler, so adding
+/// or removing a file also triggers re-expansion.
+#[cfg(feature = "nightly")]
+fn track_paths(generated: &pandoc_generator_core::Generated) {
+    for path in generated.dependencies() {
+        proc_macro::tracked::path(path.display().to_string());
+    }
+}
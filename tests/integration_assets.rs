@@ -0,0 +1,78 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = assets_mod,
+    tree_name = ASSETS_TREE,
+    content = [ compile_from_path(path: "tests/assets/content", route: "/") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    assets = ["svg"],
+    check_links = deny,
+    nproc = 1
+}
+
+// Without an asset list, files referenced from pages are still embedded
+pandoc_compile_html! {
+    mod_name = referenced_mod,
+    tree_name = REFERENCED_TREE,
+    content = [ compile_from_path(path: "tests/assets/content", route: "/") ],
+    input_format = Markdown[RebaseRelativePaths],
+    output_format = Html,
+    options = [],
+    source_ext = "md",
+    assets = [],
+    nproc = 1
+}
+
+const IMAGE: &[u8] = include_bytes!("assets/content/image.svg");
+
+#[test]
+fn test_asset_files_are_not_pages() {
+    use assets_mod::{ASSETS_TREE, ContentTree};
+
+    let names: Vec<&str> = ASSETS_TREE
+        .leaves()
+        .filter_map(|leaf| match leaf {
+            ContentTree::Html { name, .. } => Some(*name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec!["index"]);
+}
+
+#[test]
+fn test_assets_are_embedded_with_hashed_routes() {
+    use assets_mod::{ASSETS, find_asset};
+
+    assert_eq!(ASSETS.len(), 1);
+    let (route, bytes, mime) = ASSETS[0];
+    assert!(route.starts_with("/assets/image.") && route.ends_with(".svg"));
+    assert_eq!(bytes, IMAGE);
+    assert_eq!(mime, "image/svg+xml");
+    assert_eq!(find_asset(route), Some((IMAGE, "image/svg+xml")));
+    assert_eq!(find_asset("/assets/image.svg"), None);
+}
+
+#[test]
+fn test_pages_point_at_asset_routes() {
+    let page = match assets_mod::ContentTree::find_by_route("/index") {
+        Some(assets_mod::ContentTree::Html { content, .. }) => *content,
+        other => panic!("missing index page: {:?}", other),
+    };
+    assert!(page.contains(&format!("src=\"{}\"", assets_mod::ASSETS[0].0)));
+}
+
+#[test]
+fn test_referenced_files_are_embedded() {
+    use referenced_mod::{ASSETS, ContentTree};
+
+    assert_eq!(ASSETS.len(), 1);
+    assert_eq!(ASSETS[0].0, assets_mod::ASSETS[0].0);
+
+    let page = match ContentTree::find_by_route("/index") {
+        Some(ContentTree::Html { content, .. }) => *content,
+        other => panic!("missing index page: {:?}", other),
+    };
+    assert!(page.contains(&format!("src=\"{}\"", ASSETS[0].0)));
+}
@@ -0,0 +1,43 @@
+use pandoc_generator::pandoc_compile_html;
+
+// Binary formats are written to a temporary file by pandoc and embedded as bytes
+pandoc_compile_html! {
+    mod_name = docx_mod,
+    tree_name = DOCX_TREE,
+    content = [ compile_from_path(path: "tests/assets/front_matter", route: "/downloads") ],
+    input_format = Markdown,
+    output_format = Docx,
+    options = [],
+    nproc = 2
+}
+
+#[test]
+fn test_binary_leaves_carry_bytes_and_mime() {
+    use docx_mod::{ContentTree, DOCX_TREE};
+
+    let ContentTree::Nested { elements, .. } = &*DOCX_TREE else {
+        panic!("root should be a nested node");
+    };
+    let ContentTree::Nested { elements, .. } = &elements[0] else {
+        panic!("front_matter should be a nested node");
+    };
+
+    assert!(!elements.is_empty());
+    for element in elements {
+        let ContentTree::Binary { bytes, mime, .. } = element else {
+            panic!("docx output should produce Binary leaves, got {:?}", element);
+        };
+        assert!(!bytes.is_empty());
+        assert_eq!(
+            *mime,
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        );
+    }
+
+    let post = elements
+        .iter()
+        .find(|e| matches!(e, ContentTree::Binary { name: "post", .. }))
+        .expect("post should be converted");
+    let ContentTree::Binary { meta, .. } = post else { unreachable!() };
+    assert_eq!(meta.get("title").and_then(|t| t.as_str()), Some("Hello World"));
+}
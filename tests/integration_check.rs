@@ -0,0 +1,47 @@
+use pandoc_generator::pandoc_compile_html;
+
+// Fails to compile if any local link or image in the content is broken
+pandoc_compile_html! {
+    mod_name = check_mod,
+    tree_name = CHECK_TREE,
+    content = [
+        compile_from_path(path: "tests/assets/content", route: "/"),
+        compile_from_path(path: "tests/assets/links", route: "/manual")
+    ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    check_links = deny,
+    nproc = 2
+}
+
+#[test]
+fn test_checked_pages_keep_their_links() {
+    use check_mod::{CHECK_TREE, ContentTree};
+
+    let html: String = CHECK_TREE
+        .leaves()
+        .filter_map(|leaf| match leaf {
+            ContentTree::Html { content, .. } => Some(*content),
+            _ => None,
+        })
+        .collect();
+
+    assert!(html.contains(r#"src="image.svg""#));
+    assert!(html.contains(r#"href="/manual/basics/setup#install""#));
+}
+
+#[test]
+fn test_static_files_are_not_pages() {
+    use check_mod::{CHECK_TREE, ContentTree};
+
+    // `assets` is not set, `image.svg` is still not converted
+    let names: Vec<&str> = CHECK_TREE
+        .leaves()
+        .filter_map(|leaf| match leaf {
+            ContentTree::Html { name, .. } | ContentTree::Binary { name, .. } => Some(*name),
+            _ => None,
+        })
+        .collect();
+    assert!(!names.contains(&"image"), "{:?}", names);
+}
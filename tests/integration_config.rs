@@ -0,0 +1,54 @@
+use pandoc_generator::pandoc_compile_html;
+
+// Everything but the names comes from the file
+pandoc_compile_html! {
+    mod_name = config_mod,
+    tree_name = CONFIG_TREE,
+    config = "tests/assets/config/pandoc-generator.toml",
+    backend = Mock
+}
+
+// Inline arguments replace the file's values
+pandoc_compile_html! {
+    mod_name = override_mod,
+    tree_name = OVERRIDE_TREE,
+    config = "tests/assets/config/pandoc-generator.toml",
+    content = [ compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide") ],
+    output_format = Html,
+    backend = Mock
+}
+
+#[test]
+fn test_config_file_provides_the_arguments() {
+    use config_mod::{CONFIG_TREE, ContentTree, Output};
+
+    let elements = CONFIG_TREE.children();
+    assert_eq!(elements.len(), 2);
+    assert_eq!(elements[1], ContentTree::Special { ty: "footer" });
+
+    let Some(page) = ContentTree::find_by_route("/about") else {
+        panic!("about should be routed");
+    };
+    // The mock names the input format and options it converted with
+    let converted_with = r#"from markdown+smart+yaml_metadata_block with IdPrefix("doc-") -->"#;
+    assert!(matches!(page.output("html5"), Some(Output::Text(text)) if text.starts_with(&format!("<!-- mock html5 {}", converted_with))));
+    assert!(matches!(page.output("plain"), Some(Output::Text(text)) if text.starts_with(&format!("<!-- mock plain {}", converted_with))));
+
+    // The IdPrefix option from the file prefixes heading ids
+    let ContentTree::Html { headings, .. } = page else {
+        panic!("about should be an Html node");
+    };
+    assert_eq!(headings[0].id, "doc-about");
+}
+
+#[test]
+fn test_inline_arguments_override_the_config_file() {
+    use override_mod::{ContentTree, OVERRIDE_TREE};
+
+    assert_eq!(OVERRIDE_TREE.children().len(), 1);
+    let Some(page) = ContentTree::find_by_route("/guide") else {
+        panic!("guide should be routed");
+    };
+    assert!(page.output("html").is_some());
+    assert!(page.output("plain").is_none());
+}
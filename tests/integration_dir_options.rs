@@ -0,0 +1,57 @@
+use pandoc_generator::pandoc_compile_html;
+
+// The options file at the root sets an id prefix and a meta value, the one
+// in `guide/` switches to Commonmark and replaces the prefix
+pandoc_compile_html! {
+    mod_name = dir_options_mod,
+    tree_name = DIR_OPTIONS_TREE,
+    content = [ compile_from_path(path: "tests/assets/dir_options", route: "/") ],
+    backend = Mock
+}
+
+use dir_options_mod::{ContentTree, DIR_OPTIONS_TREE};
+
+fn page(route: &str) -> &'static ContentTree {
+    match ContentTree::find_by_route(route) {
+        Some(page @ ContentTree::Html { .. }) => page,
+        _ => panic!("{} should be an Html node", route),
+    }
+}
+
+fn heading_id(route: &str) -> &'static str {
+    let ContentTree::Html { headings, .. } = page(route) else { unreachable!() };
+    headings[0].id
+}
+
+/// First line of the mock output, naming what the page was converted with
+fn converted_with(route: &str) -> &'static str {
+    let ContentTree::Html { content, .. } = page(route) else { unreachable!() };
+    content.lines().next().unwrap()
+}
+
+#[test]
+fn test_options_files_are_not_sources() {
+    let names: Vec<&str> = DIR_OPTIONS_TREE
+        .leaves()
+        .map(|leaf| match leaf {
+            ContentTree::Html { name, .. } => *name,
+            _ => panic!("every page should be an Html node"),
+        })
+        .collect();
+    assert_eq!(names, ["setup", "intro"]);
+}
+
+#[test]
+fn test_options_apply_to_their_directory_and_below() {
+    assert_eq!(heading_id("/intro"), "site-intro");
+    assert_eq!(heading_id("/guide/setup"), "guide-setup");
+
+    assert_eq!(
+        converted_with("/intro"),
+        r#"<!-- mock html with IdPrefix("site-"), Meta("lang", Some("de")) -->"#
+    );
+    assert_eq!(
+        converted_with("/guide/setup"),
+        r#"<!-- mock html from commonmark+footnotes with Meta("lang", Some("de")), IdPrefix("guide-"), NumberSections -->"#
+    );
+}
@@ -0,0 +1,39 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = feed_mod,
+    tree_name = FEED_TREE,
+    content = [
+        compile_from_path(path: "tests/assets/front_matter", route: "/blog"),
+        compile_from_path(path: "tests/assets/toc", route: "/docs")
+    ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    base_url = "https://example.com/",
+    sitemap = true,
+    feed = { title: "Blog & News", from: ["tests/assets/front_matter"], limit: 5, author: "Jane" },
+    nproc = 2
+}
+
+#[test]
+fn test_sitemap_lists_every_routed_page() {
+    use feed_mod::SITEMAP_XML;
+
+    assert!(SITEMAP_XML.starts_with("<?xml"));
+    assert!(SITEMAP_XML.contains("<loc>https://example.com/blog/post</loc>\n    <lastmod>2024-05-01</lastmod>"));
+    assert!(SITEMAP_XML.contains("<loc>https://example.com/blog/plain</loc>\n  </url>"));
+    assert!(SITEMAP_XML.contains("<loc>https://example.com/docs/guide</loc>"));
+}
+
+#[test]
+fn test_feed_only_has_dated_pages_from_its_sources() {
+    use feed_mod::FEED_ATOM;
+
+    assert!(FEED_ATOM.contains("<title>Blog &amp; News</title>"));
+    assert!(FEED_ATOM.contains("<updated>2024-05-01T00:00:00Z</updated>"));
+    assert!(FEED_ATOM.contains("<name>Jane</name>"));
+    assert_eq!(FEED_ATOM.matches("<entry>").count(), 1);
+    assert!(FEED_ATOM.contains("<title>Hello World</title>\n    <id>https://example.com/blog/post</id>"));
+    assert!(!FEED_ATOM.contains("/docs/"));
+}
@@ -0,0 +1,56 @@
+use pandoc_generator::pandoc_compile_html;
+
+// One scan of the tree, every source rendered to three formats
+pandoc_compile_html! {
+    mod_name = formats_mod,
+    tree_name = FORMATS_TREE,
+    content = [ compile_from_path(path: "tests/assets/front_matter", route: "/") ],
+    input_format = Markdown,
+    output_format = [Html5, Plain, Docx],
+    options = [],
+    nproc = 4
+}
+
+fn find_page(name: &str) -> &'static formats_mod::ContentTree {
+    use formats_mod::{ContentTree, FORMATS_TREE};
+
+    FORMATS_TREE
+        .leaves()
+        .find(|e| matches!(e, ContentTree::Html { name: n, .. } if *n == name))
+        .unwrap_or_else(|| panic!("{} not found", name))
+}
+
+#[test]
+fn test_primary_format_is_content() {
+    use formats_mod::{ContentTree, Output};
+
+    let page = find_page("post");
+    let ContentTree::Html { content, outputs, .. } = page else {
+        unreachable!()
+    };
+
+    let names: Vec<&str> = outputs.iter().map(|(f, _)| *f).collect();
+    assert_eq!(names, vec!["html5", "plain", "docx"]);
+    assert_eq!(page.output("html5"), Some(&Output::Text(content)));
+    assert!(content.contains("<p>"));
+}
+
+#[test]
+fn test_secondary_formats_are_available() {
+    use formats_mod::Output;
+
+    let page = find_page("plain");
+
+    let Some(Output::Text(plain)) = page.output("plain") else {
+        panic!("plain rendering missing");
+    };
+    assert!(!plain.contains('<'), "plain text should not contain markup");
+
+    let Some(Output::Binary { bytes, mime }) = page.output("docx") else {
+        panic!("docx rendering missing");
+    };
+    assert!(!bytes.is_empty());
+    assert!(mime.ends_with("wordprocessingml.document"));
+
+    assert_eq!(page.output("latex"), None);
+}
@@ -0,0 +1,35 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = links_mod,
+    tree_name = LINKS_TREE,
+    content = [ compile_from_path(path: "tests/assets/links", route: "/manual") ],
+    input_format = Markdown,
+    output_format = [Html, Plain],
+    options = [],
+    nproc = 2
+}
+
+fn page(route: &str) -> &'static str {
+    match links_mod::ContentTree::find_by_route(route) {
+        Some(links_mod::ContentTree::Html { content, .. }) => content,
+        other => panic!("no page at {}: {:?}", route, other),
+    }
+}
+
+#[test]
+fn test_source_links_become_routes() {
+    let usage = page("/manual/guide/usage");
+
+    assert!(usage.contains(r#"<a href="/manual/basics/setup#install">the install</a>"#));
+    assert!(usage.contains(r#"<a href="/manual/basics/setup">setup</a>"#));
+    assert!(!usage.contains("01-basics"));
+}
+
+#[test]
+fn test_external_and_local_links_are_kept() {
+    let usage = page("/manual/guide/usage");
+
+    assert!(usage.contains(r#"href="https://pandoc.org/index.md""#));
+    assert!(usage.contains(r##"href="#usage""##));
+}
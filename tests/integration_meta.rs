@@ -0,0 +1,92 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = meta_mod,
+    tree_name = META_TREE,
+    content = [ compile_from_path(path: "tests/assets/front_matter", route: "/") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    nproc = 1
+}
+
+fn find_page(name: &str) -> &'static meta_mod::ContentTree {
+    use meta_mod::{ContentTree, META_TREE};
+
+    META_TREE
+        .leaves()
+        .find(|e| matches!(e, ContentTree::Html { name: n, .. } if *n == name))
+        .expect("page not found")
+}
+
+#[test]
+fn test_front_matter_strings() {
+    use meta_mod::ContentTree;
+
+    let ContentTree::Html { meta, .. } = find_page("post") else {
+        panic!("post should be an Html node");
+    };
+    assert_eq!(meta.get("title").and_then(|m| m.as_str()), Some("Hello World"));
+    assert_eq!(meta.get("date").and_then(|m| m.as_str()), Some("2024-05-01"));
+}
+
+#[test]
+fn test_front_matter_bool_list_and_map() {
+    use meta_mod::{ContentTree, Meta};
+
+    let ContentTree::Html { meta, .. } = find_page("post") else {
+        panic!("post should be an Html node");
+    };
+    assert_eq!(meta.get("draft").and_then(|m| m.as_bool()), Some(false));
+    assert_eq!(
+        meta.get("tags").and_then(|m| m.as_list()),
+        Some(&[Meta::String("rust"), Meta::String("pandoc")][..])
+    );
+    assert_eq!(
+        meta.get("author").and_then(|m| m.get("name")).and_then(|m| m.as_str()),
+        Some("Jane")
+    );
+}
+
+#[test]
+fn test_missing_front_matter_is_empty() {
+    use meta_mod::ContentTree;
+
+    let ContentTree::Html { meta, .. } = find_page("plain") else {
+        panic!("plain should be an Html node");
+    };
+    assert_eq!(meta.as_map(), Some(&[][..]));
+}
+
+pandoc_compile_html! {
+    mod_name = schema_mod,
+    tree_name = SCHEMA_TREE,
+    content = [ compile_from_path(path: "tests/assets/front_matter/post.md", route: "/post") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    nproc = 1,
+    metadata = { title: String, date: Date, draft: bool, tags: Vec<String>, weight: Option<u32> }
+}
+
+#[test]
+fn test_metadata_schema_is_typed() {
+    use schema_mod::{ContentTree, Date, Metadata, SCHEMA_TREE};
+
+    let ContentTree::Nested { elements, .. } = &*SCHEMA_TREE else {
+        panic!("root should be nested");
+    };
+    let ContentTree::Html { metadata, .. } = &elements[0] else {
+        panic!("post should be an Html node");
+    };
+    assert_eq!(
+        metadata,
+        &Metadata {
+            title: "Hello World",
+            date: Date { year: 2024, month: 5, day: 1 },
+            draft: false,
+            tags: &["rust", "pandoc"],
+            weight: None,
+        }
+    );
+}
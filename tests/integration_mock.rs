@@ -0,0 +1,50 @@
+use pandoc_generator::pandoc_compile_html;
+
+// Same threads, cache and gathering as pandoc, with output that does not
+// depend on the installed pandoc
+pandoc_compile_html! {
+    mod_name = mock_mod,
+    tree_name = MOCK_TREE,
+    content = [
+        compile_from_path(path: "tests/assets/mod_test", route: "/"),
+        compile_from_path(path: "tests/assets/front_matter/post.md", route: "/post")
+    ],
+    output_format = [Html5, Docx],
+    options = [],
+    backend = Mock,
+    nproc = 4
+}
+
+#[test]
+fn test_mock_output_is_deterministic() {
+    use mock_mod::{ContentTree, Heading, Output};
+
+    let Some(page) = ContentTree::find_by_route("/section/page1") else {
+        panic!("page1 should be routed");
+    };
+    let ContentTree::Html { content, headings, .. } = page else {
+        panic!("page1 should be an Html node");
+    };
+
+    assert_eq!(*content, "<!-- mock html5 -->\n## Page 1\n\nPage 1 content.\n<!-- /mock -->\n");
+    assert_eq!(*headings, &[Heading { level: 2, id: "page-1", text: "Page 1" }]);
+
+    let Some(Output::Binary { bytes, mime }) = page.output("docx") else {
+        panic!("docx output missing");
+    };
+    assert_eq!(*bytes, b"MOCK docx\n## Page 1\n\nPage 1 content.\n");
+    assert_eq!(*mime, "application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+}
+
+#[test]
+fn test_mock_reads_flat_front_matter() {
+    use mock_mod::ContentTree;
+
+    let Some(ContentTree::Html { meta, content, .. }) = ContentTree::find_by_route("/post") else {
+        panic!("post should be an Html node");
+    };
+
+    assert_eq!(meta.get("title").and_then(|m| m.as_str()), Some("Hello World"));
+    assert_eq!(meta.get("draft").and_then(|m| m.as_bool()), Some(false));
+    assert!(!content.contains("title:"));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "native")]
+
+use pandoc_generator::pandoc_compile_html;
+
+// No pandoc involved, these build even when it is not installed
+pandoc_compile_html! {
+    mod_name = native_mod,
+    tree_name = NATIVE_TREE,
+    content = [
+        compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide"),
+        compile_from_path(path: "tests/assets/front_matter", route: "/posts")
+    ],
+    input_format = Markdown,
+    output_format = Html5,
+    backend = Native,
+    nproc = 2
+}
+
+#[test]
+fn test_native_headings_match_pandoc_ids() {
+    use native_mod::{ContentTree, Heading};
+
+    let Some(ContentTree::Html { headings, content, .. }) = ContentTree::find_by_route("/guide") else {
+        panic!("guide should be an Html node");
+    };
+
+    assert_eq!(
+        *headings,
+        &[
+            Heading { level: 1, id: "getting-started", text: "Getting Started" },
+            Heading { level: 2, id: "installation", text: "Installation" },
+            Heading { level: 3, id: "from-source", text: "From Source" },
+            Heading { level: 2, id: "using-it", text: "Usage" },
+        ]
+    );
+    assert!(content.contains(r#"<h2 id="using-it">Usage</h2>"#));
+    assert!(content.contains("<code>--release</code>"));
+}
+
+#[test]
+fn test_native_front_matter() {
+    use native_mod::{ContentTree, Meta};
+
+    let Some(ContentTree::Html { meta, content, .. }) = ContentTree::find_by_route("/posts/post") else {
+        panic!("post should be an Html node");
+    };
+
+    assert_eq!(meta.get("title").and_then(|m| m.as_str()), Some("Hello World"));
+    assert_eq!(meta.get("draft").and_then(|m| m.as_bool()), Some(false));
+    assert_eq!(
+        meta.get("tags").and_then(|m| m.as_list()),
+        Some(&[Meta::String("rust"), Meta::String("pandoc")][..])
+    );
+    assert_eq!(
+        meta.get("author").and_then(|m| m.get("name")).and_then(|m| m.as_str()),
+        Some("Jane")
+    );
+    // The front matter itself is not rendered
+    assert!(!content.contains("Hello World\ndate"));
+    assert!(!content.contains("draft"));
+}
@@ -0,0 +1,23 @@
+use pandoc_generator::pandoc_compile_html;
+
+// Checked against `pandoc --version` and `pandoc --list-extensions` before converting
+pandoc_compile_html! {
+    mod_name = pinned_mod,
+    tree_name = PINNED_TREE,
+    content = [ compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide") ],
+    input_format = Markdown[Smart, RawHtml],
+    output_format = Html5,
+    options = [],
+    pandoc_version = ">=2.11",
+    nproc = 1
+}
+
+#[test]
+fn test_pinned_pandoc_converts() {
+    use pinned_mod::ContentTree;
+
+    let Some(ContentTree::Html { content, .. }) = ContentTree::find_by_route("/guide") else {
+        panic!("guide should be an Html node");
+    };
+    assert!(content.contains(r#"id="getting-started""#));
+}
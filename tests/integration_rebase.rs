@@ -5,6 +5,7 @@ pandoc_compile_html! {
     tree_name = MY_TREE,
     content = [ compile_from_path(path: "tests/assets/content", route: "/") ],
     input_format = Markdown[RebaseRelativePaths],
+    check_links = deny,
     output_format = Html,
     options = [],
     nproc = 1
@@ -14,21 +15,62 @@ pandoc_compile_html! {
 fn test_rebase_relative_paths() {
     use my_mod::{ContentTree, MY_TREE};
 
-    fn collect_all_html<'a>(t: &'a ContentTree, out: &mut Vec<&'a str>) {
-        match t {
-            ContentTree::Html { content, .. } => out.push(content),
-            ContentTree::Nested { elements, .. } => {
-                for e in elements {
-                    collect_all_html(e, out);
-                }
-            }
-            _ => {}
-        }
-    }
-
-    let mut all = Vec::new();
-    collect_all_html(&*MY_TREE, &mut all);
+    let all: Vec<&str> = MY_TREE
+        .leaves()
+        .filter_map(|leaf| match leaf {
+            ContentTree::Html { content, .. } => Some(*content),
+            _ => None,
+        })
+        .collect();
     assert!(!all.is_empty(), "No HTML content generated");
     let found = all.iter().any(|s| s.contains("image.svg") || s.contains("<img"));
     assert!(found, "generated HTML should reference image.svg or contain an <img> tag");
 }
+
+pandoc_compile_html! {
+    mod_name = inline_mod,
+    tree_name = INLINE_TREE,
+    content = [ compile_from_path(path: "tests/assets/content", route: "/") ],
+    input_format = Markdown[RebaseRelativePaths],
+    output_format = Html,
+    source_ext = "md",
+    options = [],
+    inline_assets_below = 8192,
+    check_links = deny,
+    nproc = 1
+}
+
+// The image is larger than the limit, so it keeps its rebased path
+pandoc_compile_html! {
+    mod_name = large_mod,
+    tree_name = LARGE_TREE,
+    content = [ compile_from_path(path: "tests/assets/content", route: "/") ],
+    input_format = Markdown[RebaseRelativePaths],
+    output_format = Html,
+    source_ext = "md",
+    options = [],
+    inline_assets_below = 16,
+    nproc = 1
+}
+
+#[test]
+fn test_small_images_are_inlined() {
+    let page = match inline_mod::ContentTree::find_by_route("/index") {
+        Some(inline_mod::ContentTree::Html { content, .. }) => *content,
+        other => panic!("missing index page: {:?}", other),
+    };
+
+    assert!(page.contains(r#"<img src="data:image/svg+xml;base64,"#));
+    assert!(!page.contains("image.svg"));
+}
+
+#[test]
+fn test_large_images_are_referenced() {
+    let page = match large_mod::ContentTree::find_by_route("/index") {
+        Some(large_mod::ContentTree::Html { content, .. }) => *content,
+        other => panic!("missing index page: {:?}", other),
+    };
+
+    assert!(page.contains("image.svg\""));
+    assert!(!page.contains("data:"));
+}
@@ -0,0 +1,68 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = search_mod,
+    tree_name = SEARCH_TREE,
+    content = [
+        compile_from_path(path: "tests/assets/front_matter", route: "/blog"),
+        compile_from_path(path: "tests/assets/toc", route: "/docs")
+    ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    search_index = true,
+    search_json = true,
+    nproc = 2
+}
+
+fn name_of(node: &search_mod::ContentTree) -> &'static str {
+    match node {
+        search_mod::ContentTree::Html { name, .. } => name,
+        _ => panic!("search should only return pages"),
+    }
+}
+
+#[test]
+fn test_index_is_sorted() {
+    use search_mod::SEARCH_INDEX;
+
+    assert!(SEARCH_INDEX.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(SEARCH_INDEX.iter().all(|(term, _)| *term == term.to_lowercase()));
+}
+
+#[test]
+fn test_search_finds_body_and_title_terms() {
+    use search_mod::search;
+
+    let hits = search("installer");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(name_of(hits[0].0), "guide");
+
+    // The front matter title is indexed too
+    let hits = search("HELLO");
+    assert_eq!(hits.iter().map(|(n, _)| name_of(n)).collect::<Vec<_>>(), vec!["post"]);
+
+    assert!(search("xylophone").is_empty());
+    assert!(search("").is_empty());
+}
+
+#[test]
+fn test_more_matching_terms_rank_higher() {
+    use search_mod::search;
+
+    let hits = search("repository page");
+    assert_eq!(hits.len(), 2);
+    assert!(hits[0].1 >= hits[1].1);
+
+    let hits = search("clone the repository");
+    assert_eq!(name_of(hits[0].0), "guide");
+}
+
+#[test]
+fn test_elasticlunr_json_is_emitted() {
+    use search_mod::SEARCH_INDEX_JSON;
+
+    assert!(SEARCH_INDEX_JSON.starts_with('{'));
+    assert!(SEARCH_INDEX_JSON.contains("\"ref\":\"id\""));
+    assert!(SEARCH_INDEX_JSON.contains("\"route\":\"/docs/guide\""));
+}
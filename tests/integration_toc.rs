@@ -0,0 +1,59 @@
+use pandoc_generator::pandoc_compile_html;
+
+pandoc_compile_html! {
+    mod_name = toc_mod,
+    tree_name = TOC_TREE,
+    content = [ compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide") ],
+    input_format = Markdown,
+    output_format = Html5,
+    options = [],
+    nproc = 1
+}
+
+pandoc_compile_html! {
+    mod_name = shallow_toc_mod,
+    tree_name = SHALLOW_TOC_TREE,
+    content = [ compile_from_path(path: "tests/assets/toc/guide.md", route: "/guide") ],
+    input_format = Markdown,
+    output_format = Html5,
+    options = [],
+    toc_depth = 2,
+    nproc = 1
+}
+
+#[test]
+fn test_headings_follow_document_order() {
+    use toc_mod::{ContentTree, Heading};
+
+    let Some(ContentTree::Html { headings, content, .. }) = ContentTree::find_by_route("/guide") else {
+        panic!("guide should be an Html node");
+    };
+
+    // The default depth of 3 drops the level 4 heading
+    assert_eq!(
+        *headings,
+        &[
+            Heading { level: 1, id: "getting-started", text: "Getting Started" },
+            Heading { level: 2, id: "installation", text: "Installation" },
+            Heading { level: 3, id: "from-source", text: "From Source" },
+            Heading { level: 2, id: "using-it", text: "Usage" },
+        ]
+    );
+
+    // Ids match the anchors in the rendered page
+    for heading in headings.iter() {
+        assert!(content.contains(&format!("id=\"{}\"", heading.id)));
+    }
+}
+
+#[test]
+fn test_toc_depth_limits_headings() {
+    use shallow_toc_mod::ContentTree;
+
+    let Some(ContentTree::Html { headings, .. }) = ContentTree::find_by_route("/guide") else {
+        panic!("guide should be an Html node");
+    };
+
+    let levels: Vec<u8> = headings.iter().map(|h| h.level).collect();
+    assert_eq!(levels, vec![1, 2, 2]);
+}
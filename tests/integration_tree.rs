@@ -17,20 +17,13 @@ pandoc_compile_html! {
 fn test_single_file_pipeline() {
     use single_mod::{ContentTree, SINGLE_TREE};
 
-    fn collect_all_html<'a>(t: &'a ContentTree, out: &mut Vec<&'a str>) {
-        match t {
-            ContentTree::Html { content, .. } => out.push(content),
-            ContentTree::Nested { elements, .. } => {
-                for e in elements {
-                    collect_all_html(e, out);
-                }
-            }
-            _ => {}
-        }
-    }
-
-    let mut all = Vec::new();
-    collect_all_html(&*SINGLE_TREE, &mut all);
+    let all: Vec<&str> = SINGLE_TREE
+        .leaves()
+        .filter_map(|leaf| match leaf {
+            ContentTree::Html { content, .. } => Some(*content),
+            _ => None,
+        })
+        .collect();
     assert!(!all.is_empty(), "No HTML content generated");
     let found = all.iter().any(|s| s.contains("image.svg") || s.contains("<img"));
     assert!(found, "generated HTML should reference image.svg or contain an <img> tag");
@@ -51,15 +44,7 @@ pandoc_compile_html! {
 fn test_directory_pipeline() {
     use dir_mod::{ContentTree, DIR_TREE};
 
-    fn count_html_nodes(t: &ContentTree) -> usize {
-        match t {
-            ContentTree::Html { .. } => 1,
-            ContentTree::Nested { elements, .. } => elements.iter().map(count_html_nodes).sum(),
-            _ => 0,
-        }
-    }
-
-    let count = count_html_nodes(&*DIR_TREE);
+    let count = DIR_TREE.leaves().filter(|n| matches!(n, ContentTree::Html { .. })).count();
     assert!(count >= 1, "Should have generated at least one HTML node");
 }
 
@@ -78,27 +63,10 @@ pandoc_compile_html! {
 fn test_with_route() {
     use route_mod::{ContentTree, ROUTE_TREE};
 
-    fn check_route(t: &ContentTree, found: &mut bool) {
-        match t {
-            ContentTree::Html { route, .. } => {
-                if route.is_some() {
-                    *found = true;
-                }
-            }
-            ContentTree::Nested { route, elements, .. } => {
-                if route.is_some() {
-                    *found = true;
-                }
-                for e in elements {
-                    check_route(e, found);
-                }
-            }
-            _ => {}
-        }
-    }
-
-    let mut found = false;
-    check_route(&*ROUTE_TREE, &mut found);
+    let found = ROUTE_TREE.iter().any(|n| match n {
+        ContentTree::Html { route, .. } | ContentTree::Nested { route, .. } => route.is_some(),
+        _ => false,
+    });
     assert!(found, "Route should be set when provided");
 }
 
@@ -120,16 +88,7 @@ fn test_mod_file_creates_nested_node() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Verify the tree has nested structure for sections
-    fn count_all_nested(t: &ContentTree) -> usize {
-        match t {
-            ContentTree::Nested { elements, .. } => {
-                1 + elements.iter().map(count_all_nested).sum::<usize>()
-            }
-            ContentTree::Html { .. } | ContentTree::Special { .. } => 0,
-        }
-    }
-
-    let nested_count = count_all_nested(&*MOD_TEST_TREE);
+    let nested_count = MOD_TEST_TREE.iter().filter(|n| matches!(n, ContentTree::Nested { .. })).count();
     assert!(nested_count >= 2, "Should have at least 2 nested nodes (sections)");
 }
 
@@ -138,20 +97,13 @@ fn test_mod_file_content_in_nested_node() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Collect all nested node names
-    fn collect_nested_names(t: &ContentTree, names: &mut Vec<&str>) {
-        match t {
-            ContentTree::Nested { name, elements, .. } => {
-                names.push(name);
-                for e in elements {
-                    collect_nested_names(e, names);
-                }
-            }
-            ContentTree::Html { .. } | ContentTree::Special { .. } => {}
-        }
-    }
-
-    let mut names = Vec::new();
-    collect_nested_names(&*MOD_TEST_TREE, &mut names);
+    let names: Vec<&str> = MOD_TEST_TREE
+        .iter()
+        .filter_map(|n| match n {
+            ContentTree::Nested { name, .. } => Some(*name),
+            _ => None,
+        })
+        .collect();
 
     // Should have section and subsection as nested nodes
     assert!(names.contains(&"section"), "Should have 'section' as a nested node");
@@ -160,23 +112,10 @@ fn test_mod_file_content_in_nested_node() {
 
 #[test]
 fn test_deeply_nested_structure_preserved() {
-    use mod_test_mod::{ContentTree, MOD_TEST_TREE};
+    use mod_test_mod::MOD_TEST_TREE;
 
     // Count depth levels
-    fn get_depth(t: &ContentTree, current: usize) -> usize {
-        match t {
-            ContentTree::Nested { elements, .. } => {
-                if elements.is_empty() {
-                    current
-                } else {
-                    elements.iter().map(|e| get_depth(e, current + 1)).max().unwrap_or(current)
-                }
-            }
-            ContentTree::Html { .. } | ContentTree::Special { .. } => current,
-        }
-    }
-
-    let depth = get_depth(&*MOD_TEST_TREE, 0);
+    let depth = MOD_TEST_TREE.walk_with_depth().map(|(_, depth)| depth).max().unwrap_or(0);
     assert!(depth >= 3, "Should have at least 3 levels of nesting");
 }
 
@@ -185,22 +124,13 @@ fn test_mod_file_html_content_in_nested() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Collect HTML content from nested nodes (mod files become nested nodes with HTML)
-    fn collect_nested_html(t: &ContentTree, htmls: &mut Vec<&str>) {
-        match t {
-            ContentTree::Nested { elements, .. } => {
-                for e in elements {
-                    collect_nested_html(e, htmls);
-                }
-            }
-            ContentTree::Html { content, .. } => {
-                htmls.push(content);
-            }
-            ContentTree::Special { .. } => {}
-        }
-    }
-
-    let mut htmls = Vec::new();
-    collect_nested_html(&*MOD_TEST_TREE, &mut htmls);
+    let htmls: Vec<&str> = MOD_TEST_TREE
+        .leaves()
+        .filter_map(|n| match n {
+            ContentTree::Html { content, .. } => Some(*content),
+            _ => None,
+        })
+        .collect();
 
     // Verify some expected content is present
     let combined = htmls.join(" ");
@@ -213,15 +143,7 @@ fn test_section_without_mod_file_still_nestable() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Count all HTML nodes (files become Html nodes)
-    fn count_html_nodes(t: &ContentTree) -> usize {
-        match t {
-            ContentTree::Html { .. } => 1,
-            ContentTree::Nested { elements, .. } => elements.iter().map(count_html_nodes).sum(),
-            ContentTree::Special { .. } => 0,
-        }
-    }
-
-    let count = count_html_nodes(&*MOD_TEST_TREE);
+    let count = MOD_TEST_TREE.leaves().filter(|n| matches!(n, ContentTree::Html { .. })).count();
     // Should have: index.md, about.md, page1.md, page2.md, detail.md, info.md = 6 HTML nodes
     assert!(count >= 6, "Should have at least 6 HTML nodes (files)");
 }
@@ -241,22 +163,9 @@ fn test_route_on_nested_nodes() {
     use route_nested_mod::{ContentTree, ROUTE_NESTED_TREE};
 
     // Check that nested nodes have routes set
-    fn check_nested_routes(t: &ContentTree, found: &mut bool) {
-        match t {
-            ContentTree::Nested { route, elements, .. } => {
-                if route.is_some() {
-                    *found = true;
-                }
-                for e in elements {
-                    check_nested_routes(e, found);
-                }
-            }
-            ContentTree::Html { .. } | ContentTree::Special { .. } => {}
-        }
-    }
-
-    let mut found = false;
-    check_nested_routes(&*ROUTE_NESTED_TREE, &mut found);
+    let found = ROUTE_NESTED_TREE
+        .iter()
+        .any(|n| matches!(n, ContentTree::Nested { route: Some(_), .. }));
     assert!(found, "Nested nodes should have routes when provided");
 }
 
@@ -265,16 +174,7 @@ fn test_multiple_mod_files_produce_multiple_nested_nodes() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Count all Nested nodes
-    fn count_all_nested(t: &ContentTree) -> usize {
-        match t {
-            ContentTree::Nested { elements, .. } => {
-                1 + elements.iter().map(count_all_nested).sum::<usize>()
-            }
-            ContentTree::Html { .. } | ContentTree::Special { .. } => 0,
-        }
-    }
-
-    let nested_count = count_all_nested(&*MOD_TEST_TREE);
+    let nested_count = MOD_TEST_TREE.iter().filter(|n| matches!(n, ContentTree::Nested { .. })).count();
     // Should have at least: section, subsection, and potentially more
     assert!(nested_count >= 2, "Should have multiple nested nodes from mod files");
 }
@@ -284,22 +184,13 @@ fn test_regular_files_become_html_nodes() {
     use mod_test_mod::{ContentTree, MOD_TEST_TREE};
 
     // Collect all HTML node names
-    fn collect_html_names(t: &ContentTree, names: &mut Vec<&str>) {
-        match t {
-            ContentTree::Html { name, .. } => {
-                names.push(name);
-            }
-            ContentTree::Nested { elements, .. } => {
-                for e in elements {
-                    collect_html_names(e, names);
-                }
-            }
-            ContentTree::Special { .. } => {}
-        }
-    }
-
-    let mut names = Vec::new();
-    collect_html_names(&*MOD_TEST_TREE, &mut names);
+    let names: Vec<&str> = MOD_TEST_TREE
+        .leaves()
+        .filter_map(|n| match n {
+            ContentTree::Html { name, .. } => Some(*name),
+            _ => None,
+        })
+        .collect();
 
     // Should have regular files as HTML nodes
     assert!(names.contains(&"index"), "Should have 'index' as an HTML node");
@@ -309,3 +200,249 @@ fn test_regular_files_become_html_nodes() {
     assert!(names.contains(&"detail"), "Should have 'detail' as an HTML node");
     assert!(names.contains(&"info"), "Should have 'info' as an HTML node");
 }
+
+// ── Rebuild tracking ─────────────────────────────────────────────────────────
+
+#[test]
+fn test_all_sources_are_tracked() {
+    use mod_test_mod::SOURCE_FILES;
+
+    fn collect_sources(dir: &std::path::Path, out: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect_sources(&path, out);
+            } else {
+                out.push(path);
+            }
+        }
+    }
+
+    let mut expected = Vec::new();
+    collect_sources(
+        &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/mod_test"),
+        &mut expected,
+    );
+
+    // Files shadowed by a __mod__ file are not compiled, everything else must be tracked
+    let compiled: Vec<_> = expected
+        .into_iter()
+        .filter(|p| {
+            let in_mod_dir = p.parent().unwrap().join("__mod__.md").is_file();
+            !in_mod_dir || p.file_name().unwrap() == "__mod__.md"
+        })
+        .collect();
+
+    for path in compiled.iter() {
+        let path = std::fs::canonicalize(path).unwrap();
+        let tracked = SOURCE_FILES
+            .iter()
+            .find(|(p, _)| std::path::Path::new(p) == path)
+            .unwrap_or_else(|| panic!("{:?} is not tracked", path));
+        assert_eq!(tracked.1, std::fs::read(&path).unwrap().as_slice());
+    }
+}
+
+// ── Path resolution ──────────────────────────────────────────────────────────
+
+pandoc_compile_html! {
+    mod_name = base_mod,
+    tree_name = BASE_TREE,
+    content = [ compile_from_path(path: "mod_test", route: "/") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    base = "tests/assets",
+    nproc = 1
+}
+
+pandoc_compile_html! {
+    mod_name = env_mod,
+    tree_name = ENV_TREE,
+    content = [ compile_from_path(path: "${CARGO_MANIFEST_DIR}/tests/assets/mod_test", route: "/") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    nproc = 1
+}
+
+#[test]
+fn test_base_and_env_paths_resolve_to_same_tree() {
+    let count = base_mod::BASE_TREE.leaves().count();
+    assert!(count >= 6, "base-relative path should find the mod_test sources");
+    assert_eq!(count, env_mod::ENV_TREE.leaves().count());
+}
+
+// ── Route lookup ─────────────────────────────────────────────────────────────
+
+pandoc_compile_html! {
+    mod_name = lookup_mod,
+    tree_name = LOOKUP_TREE,
+    content = [ special(ty: "nav"), compile_from_path(path: "tests/assets/mod_test", route: "/docs/") ],
+    input_format = Markdown,
+    output_format = Html,
+    options = [],
+    nproc = 1
+}
+
+#[test]
+fn test_children_get_full_routes() {
+    use lookup_mod::ContentTree;
+
+    let Some(ContentTree::Nested { elements, route, .. }) = ContentTree::find_by_route("/docs") else {
+        panic!("/docs should resolve to the content root");
+    };
+    assert_eq!(*route, Some("/docs/"));
+
+    // `01-section` and `02-section` share a slug, the second one is numbered
+    let routes: Vec<&str> = elements
+        .iter()
+        .filter_map(|element| match element {
+            ContentTree::Html { route, .. } | ContentTree::Nested { route, .. } => *route,
+            ContentTree::Binary { .. } | ContentTree::Special { .. } => None,
+        })
+        .collect();
+    assert_eq!(routes, ["/docs/section", "/docs/section-2", "/docs/about", "/docs/index"]);
+}
+
+#[test]
+fn test_find_by_route() {
+    use lookup_mod::{ContentTree, ROUTES};
+
+    assert!(ROUTES.windows(2).all(|w| w[0].0 < w[1].0), "ROUTES should be sorted");
+
+    let Some(ContentTree::Html { name, content, .. }) = ContentTree::find_by_route("/docs/about") else {
+        panic!("/docs/about should resolve to a page");
+    };
+    assert_eq!(*name, "about");
+    assert!(!content.is_empty());
+
+    assert!(matches!(
+        ContentTree::find_by_route("/docs/section/page1/"),
+        Some(ContentTree::Html { name: "page1", .. })
+    ));
+    assert!(matches!(
+        ContentTree::find_by_route("/docs/section-2/info"),
+        Some(ContentTree::Html { name: "info", .. })
+    ));
+    assert_eq!(ContentTree::find_by_route("/docs/missing"), None);
+    assert_eq!(ContentTree::find_by_route("/"), None);
+}
+
+// ── Traversal API ────────────────────────────────────────────────────────────
+
+#[test]
+fn test_iter_is_depth_first_in_tree_order() {
+    use lookup_mod::{ContentTree, LOOKUP_TREE};
+
+    let nodes: Vec<&ContentTree> = LOOKUP_TREE.iter().collect();
+    assert!(std::ptr::eq(nodes[0], &*LOOKUP_TREE), "iteration starts at the node itself");
+    assert!(matches!(nodes[1], ContentTree::Special { ty: "nav" }));
+
+    // Every node comes right after its parent or after a sibling's subtree
+    for (i, node) in nodes.iter().enumerate().skip(1) {
+        let parent = LOOKUP_TREE.parent_of(node).expect("every node but the root has a parent");
+        let parent_pos = nodes.iter().position(|n| std::ptr::eq(*n, parent)).unwrap();
+        assert!(parent_pos < i);
+    }
+
+    let depths: Vec<usize> = LOOKUP_TREE.walk_with_depth().map(|(_, d)| d).collect();
+    assert_eq!(depths.len(), nodes.len());
+    assert_eq!(depths[0], 0);
+    assert!(depths.windows(2).all(|w| w[1] <= w[0] + 1));
+}
+
+#[test]
+fn test_parent_and_path_lookup() {
+    use lookup_mod::{ContentTree, LOOKUP_TREE};
+
+    let page = ContentTree::find_by_route("/docs/section/page1").unwrap();
+    let path = LOOKUP_TREE.path_to(page).expect("page should be reachable from the root");
+    let names: Vec<&str> = path
+        .iter()
+        .map(|n| match n {
+            ContentTree::Nested { name, .. } | ContentTree::Html { name, .. } => *name,
+            _ => "",
+        })
+        .collect();
+    assert_eq!(names, vec!["ROOT", "mod_test", "section", "page1"]);
+
+    assert!(std::ptr::eq(LOOKUP_TREE.parent_of(page).unwrap(), path[2]));
+    assert!(LOOKUP_TREE.parent_of(&LOOKUP_TREE).is_none());
+    assert!(page.children().is_empty());
+}
+
+#[test]
+fn test_visitor_defaults_recurse_into_nested() {
+    use lookup_mod::{ContentTree, LOOKUP_TREE, Visitor};
+
+    #[derive(Default)]
+    struct Names<'a> {
+        specials: usize,
+        pages: Vec<&'a str>,
+    }
+
+    impl<'a> Visitor<'a> for Names<'a> {
+        fn visit_special(&mut self, _node: &'a ContentTree) {
+            self.specials += 1;
+        }
+
+        fn visit_html(&mut self, node: &'a ContentTree) {
+            if let ContentTree::Html { name, .. } = node {
+                self.pages.push(name);
+            }
+        }
+    }
+
+    let mut names = Names::default();
+    LOOKUP_TREE.accept(&mut names);
+
+    assert_eq!(names.specials, 1);
+    assert_eq!(names.pages.len(), LOOKUP_TREE.leaves().count());
+    assert!(names.pages.contains(&"about"));
+}
+
+// ── Navigation ───────────────────────────────────────────────────────────────
+
+#[test]
+fn test_prev_next_follow_reading_order() {
+    use lookup_mod::{LOOKUP_TREE, READING_ORDER};
+
+    let leaves: Vec<_> = LOOKUP_TREE.leaves().collect();
+    assert_eq!(leaves.len(), READING_ORDER.len());
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        assert_eq!(leaf.order(), Some(i));
+    }
+
+    assert!(leaves[0].prev().is_none());
+    assert!(leaves.last().unwrap().next().is_none());
+
+    // Walking `next` from the first page visits every leaf exactly once
+    let mut walked = vec![leaves[0]];
+    while let Some(next) = walked.last().unwrap().next() {
+        assert!(std::ptr::eq(next.prev().unwrap(), *walked.last().unwrap()));
+        walked.push(next);
+    }
+    assert_eq!(walked.len(), leaves.len());
+    assert!(walked.iter().zip(&leaves).all(|(a, b)| std::ptr::eq(*a, *b)));
+
+    assert_eq!(LOOKUP_TREE.order(), None);
+    assert!(LOOKUP_TREE.next().is_none());
+}
+
+#[test]
+fn test_breadcrumbs() {
+    use lookup_mod::{ContentTree, LOOKUP_TREE};
+
+    let page = ContentTree::find_by_route("/docs/section/page1").unwrap();
+    assert_eq!(
+        page.breadcrumbs(),
+        &[
+            ("mod_test", Some("/docs/")),
+            ("section", Some("/docs/section")),
+            ("page1", Some("/docs/section/page1")),
+        ]
+    );
+    assert!(LOOKUP_TREE.breadcrumbs().is_empty());
+}